@@ -0,0 +1,30 @@
+use bevy_chess::{fen::START_FEN, position::Position};
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+
+const KIWIPETE: &str = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+
+fn legal_moves(c: &mut Criterion) {
+    let start = Position::from_fen(START_FEN).unwrap();
+    let kiwipete = Position::from_fen(KIWIPETE).unwrap();
+    c.bench_function("legal_moves start", |b| {
+        b.iter(|| black_box(&start).legal_moves())
+    });
+    c.bench_function("legal_moves kiwipete", |b| {
+        b.iter(|| black_box(&kiwipete).legal_moves())
+    });
+}
+
+fn perft(c: &mut Criterion) {
+    let start = Position::from_fen(START_FEN).unwrap();
+    let kiwipete = Position::from_fen(KIWIPETE).unwrap();
+    let mut group = c.benchmark_group("perft");
+    group.sample_size(10);
+    group.bench_function("start depth 4", |b| b.iter(|| black_box(&start).perft(4)));
+    group.bench_function("kiwipete depth 3", |b| {
+        b.iter(|| black_box(&kiwipete).perft(3))
+    });
+    group.finish();
+}
+
+criterion_group!(benches, legal_moves, perft);
+criterion_main!(benches);
@@ -0,0 +1,257 @@
+use crate::{board::*, menu::*};
+use bevy::prelude::*;
+use bevy_chess::{
+    pgn::GameRecord,
+    position::{Move, Position},
+    search::{search, SearchLimits},
+    transposition::TranspositionTable,
+    uci::UciEngine,
+};
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    thread,
+    time::Duration,
+};
+
+/// How long the engines think, and which UCI engine to run.
+/// Add this resource before `AiPlugin` to change it.
+pub struct EngineSettings {
+    /// Deepest search the built-in engine runs, however much time is left
+    pub depth: Option<u32>,
+    pub think_time: Duration,
+    /// Command line of the external engine, for players set to `PlayerKind::Uci`
+    pub uci_command: Option<String>,
+}
+impl Default for EngineSettings {
+    fn default() -> Self {
+        Self {
+            depth: None,
+            think_time: Duration::from_secs(1),
+            uci_command: None,
+        }
+    }
+}
+
+/// Search running on its own thread, so the frame loop doesn't stall while the engine thinks
+struct EngineTask {
+    /// Hash of the position being searched, to spot when the board changed underneath it
+    hash: u64,
+    stop: Arc<AtomicBool>,
+    result: Arc<Mutex<Option<Option<Move>>>>,
+}
+
+impl EngineTask {
+    fn start(
+        position: &Position,
+        settings: &EngineSettings,
+        table: Arc<Mutex<TranspositionTable>>,
+    ) -> Self {
+        let stop = Arc::new(AtomicBool::new(false));
+        let result = Arc::new(Mutex::new(None));
+        let limits = SearchLimits {
+            depth: settings.depth,
+            time: Some(settings.think_time),
+            stop: Some(stop.clone()),
+        };
+
+        let thread_position = position.clone();
+        let thread_result = result.clone();
+        thread::spawn(move || {
+            let mut table = table.lock().unwrap();
+            let best_move = search(&thread_position, &limits, &mut table, |_| {})
+                .and_then(|info| info.best_move());
+            *thread_result.lock().unwrap() = Some(best_move);
+        });
+
+        Self {
+            hash: position.hash(),
+            stop,
+            result,
+        }
+    }
+
+    /// Returns the search's move once it's done
+    fn poll(&self) -> Option<Option<Move>> {
+        self.result.lock().unwrap().take()
+    }
+}
+
+impl Drop for EngineTask {
+    fn drop(&mut self) {
+        // Let an abandoned search finish early instead of using up a core
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+#[derive(Default)]
+struct EngineState {
+    task: Option<EngineTask>,
+    /// Kept between moves, since the positions searched for one move come up again in the next
+    table: Arc<Mutex<TranspositionTable>>,
+}
+
+/// Starts a search whenever it's the engine's turn, and plays its move once it's found
+fn run_engine(
+    mut state: ResMut<EngineState>,
+    settings: Res<EngineSettings>,
+    players: Res<Players>,
+    turn: Res<PlayerTurn>,
+    position: Res<Position>,
+    status: Res<GameStatus>,
+    upcoming_moves: Res<UpcomingMoves>,
+    mut make_move_events: ResMut<Events<MakeMoveEvent>>,
+) {
+    // Stepping through undone or loaded moves pauses the engine, so they aren't replaced
+    let engine_to_move = players.get(turn.0) == PlayerKind::Engine
+        && status.game_over.is_none()
+        && upcoming_moves.moves.is_empty();
+    if !engine_to_move {
+        state.task = None;
+        return;
+    }
+
+    let hash = position.hash();
+    if state.task.as_ref().is_some_and(|task| task.hash != hash) {
+        state.task = None;
+    }
+
+    match &state.task {
+        None => state.task = Some(EngineTask::start(&position, &settings, state.table.clone())),
+        Some(task) => {
+            if let Some(best_move) = task.poll() {
+                if let Some(best_move) = best_move {
+                    make_move_events.send(MakeMoveEvent(best_move));
+                }
+                state.task = None;
+            }
+        }
+    }
+}
+
+/// Starts each new game with an empty transposition table, since the old game's entries are no use
+fn reset_engine(
+    mut event_reader: Local<EventReader<NewGameEvent>>,
+    events: Res<Events<NewGameEvent>>,
+    mut state: ResMut<EngineState>,
+) {
+    if event_reader.iter(&events).next().is_some() {
+        // A search still running keeps the old table to itself, so there's no waiting for it
+        state.task = None;
+        state.table = Arc::new(Mutex::new(TranspositionTable::default()));
+    }
+}
+
+/// External engine process, once it's been started
+#[derive(Default)]
+struct ExternalEngine {
+    // The engine reads its replies through a channel receiver, which can't be shared
+    engine: Option<Mutex<UciEngine>>,
+    /// Position the engine is searching
+    searching_fen: Option<String>,
+}
+
+/// Starts the UCI engine if a side uses it. If it can't start, the built-in engine plays instead.
+fn start_external_engine(
+    settings: Res<EngineSettings>,
+    mut players: ResMut<Players>,
+    mut external: ResMut<ExternalEngine>,
+) {
+    if players.white != PlayerKind::Uci && players.black != PlayerKind::Uci {
+        return;
+    }
+
+    let engine = match &settings.uci_command {
+        Some(command) => UciEngine::spawn(command, Duration::from_secs(10))
+            .map_err(|err| format!("Couldn't start UCI engine '{}': {}", command, err)),
+        None => Err("No UCI engine command given".to_string()),
+    };
+    match engine {
+        Ok(engine) => external.engine = Some(Mutex::new(engine)),
+        Err(err) => {
+            eprintln!("{}, using the built-in engine", err);
+            use_built_in_engine(&mut players);
+        }
+    }
+}
+
+fn use_built_in_engine(players: &mut Players) {
+    if players.white == PlayerKind::Uci {
+        players.white = PlayerKind::Engine;
+    }
+    if players.black == PlayerKind::Uci {
+        players.black = PlayerKind::Engine;
+    }
+}
+
+/// Sends the game to the UCI engine whenever it's its turn, and plays its move once it replies
+fn run_external_engine(
+    mut external: ResMut<ExternalEngine>,
+    settings: Res<EngineSettings>,
+    mut players: ResMut<Players>,
+    turn: Res<PlayerTurn>,
+    position: Res<Position>,
+    record: Res<GameRecord>,
+    status: Res<GameStatus>,
+    upcoming_moves: Res<UpcomingMoves>,
+    mut make_move_events: ResMut<Events<MakeMoveEvent>>,
+) {
+    let external = &mut *external;
+    let mut engine = match &external.engine {
+        Some(engine) => engine.lock().unwrap(),
+        None => return,
+    };
+
+    let engine_to_move = players.get(turn.0) == PlayerKind::Uci
+        && status.game_over.is_none()
+        && upcoming_moves.moves.is_empty();
+    let fen = position.to_fen();
+
+    let result = if !engine_to_move {
+        external.searching_fen = None;
+        engine.stop().map(|_| None)
+    } else if external.searching_fen.as_ref() != Some(&fen) {
+        // The record holds the moves leading to the board, so the engine can see repetitions
+        external.searching_fen = Some(fen);
+        engine
+            .start_search(&record.start, &record.moves, settings.think_time)
+            .map(|_| None)
+    } else {
+        engine.poll_best_move()
+    };
+
+    match result {
+        Ok(Some(best_move)) => {
+            make_move_events.send(MakeMoveEvent(best_move));
+        }
+        Ok(None) => {}
+        Err(err) => {
+            eprintln!(
+                "UCI engine stopped working, using the built-in engine: {}",
+                err
+            );
+            drop(engine);
+            external.engine = None;
+            use_built_in_engine(&mut players);
+        }
+    }
+}
+
+pub struct AiPlugin;
+impl Plugin for AiPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<EngineSettings>()
+            .init_resource::<EngineState>()
+            .init_resource::<ExternalEngine>()
+            .add_startup_system(start_external_engine.system())
+            .add_system(reset_engine.system())
+            .on_state_update(APP_STATE_STAGE, AppState::Playing, run_engine.system())
+            .on_state_update(
+                APP_STATE_STAGE,
+                AppState::Playing,
+                run_external_engine.system(),
+            );
+    }
+}
@@ -0,0 +1,207 @@
+//! Speaks the Universal Chess Interface over stdin and stdout, so the built-in engine can
+//! play in chess GUIs and tournament managers without the 3D frontend.
+
+use bevy_chess::{
+    position::{PieceColor, Position},
+    search::{search, SearchInfo, SearchLimits},
+    transposition::TranspositionTable,
+    uci::{move_to_uci, score_to_uci},
+};
+use std::{
+    io::{self, BufRead},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+/// Search running on its own thread, so `stop` can still be read while it thinks
+struct RunningSearch {
+    stop: Arc<AtomicBool>,
+    handle: JoinHandle<()>,
+}
+
+impl RunningSearch {
+    /// Stops the search, waiting for it to send its `bestmove`
+    fn stop(self) {
+        self.stop.store(true, Ordering::Relaxed);
+        let _ = self.handle.join();
+    }
+}
+
+fn main() {
+    let mut position = Position::default();
+    let mut running: Option<RunningSearch> = None;
+    let mut table = Arc::new(Mutex::new(TranspositionTable::default()));
+
+    for line in io::stdin().lock().lines() {
+        let line = match line {
+            Ok(line) => line,
+            Err(_) => break,
+        };
+        let line = line.trim();
+        let (command, args) = match line.find(char::is_whitespace) {
+            Some(i) => (&line[..i], line[i..].trim()),
+            None => (line, ""),
+        };
+
+        match command {
+            "uci" => {
+                println!("id name Bevy Chess");
+                println!("id author guimcaballero");
+                println!("option name Hash type spin default 16 min 1 max 1024");
+                println!("uciok");
+            }
+            "isready" => println!("readyok"),
+            "setoption" => {
+                // Only `setoption name Hash value <megabytes>` is supported
+                let words: Vec<&str> = args.split_whitespace().collect();
+                if let ["name", "Hash", "value", megabytes] = words.as_slice() {
+                    if let Ok(megabytes) = megabytes.parse::<usize>() {
+                        if let Some(search) = running.take() {
+                            search.stop();
+                        }
+                        table = Arc::new(Mutex::new(TranspositionTable::new(
+                            megabytes.clamp(1, 1024),
+                        )));
+                    }
+                }
+            }
+            "ucinewgame" => {
+                if let Some(search) = running.take() {
+                    search.stop();
+                }
+                position = Position::default();
+                table.lock().unwrap().clear();
+            }
+            "position" => {
+                if let Some(search) = running.take() {
+                    search.stop();
+                }
+                match Position::from_uci_position(args) {
+                    Some(new_position) => position = new_position,
+                    None => println!("info string invalid position '{}'", args),
+                }
+            }
+            "go" => {
+                if let Some(search) = running.take() {
+                    search.stop();
+                }
+                match args.strip_prefix("perft") {
+                    Some(depth) => perft(&position, depth),
+                    None => running = Some(go(&position, args, table.clone())),
+                }
+            }
+            "stop" => {
+                if let Some(search) = running.take() {
+                    search.stop();
+                }
+            }
+            "quit" => break,
+            // Unknown commands are ignored, as the protocol asks
+            _ => {}
+        }
+    }
+
+    if let Some(search) = running.take() {
+        search.stop();
+    }
+}
+
+/// Starts searching with the limits of a `go` command
+fn go(position: &Position, args: &str, table: Arc<Mutex<TranspositionTable>>) -> RunningSearch {
+    let mut depth = None;
+    let mut movetime = None;
+    let mut time_left = None;
+    let mut increment = 0;
+    let mut moves_to_go = None;
+    let mut infinite = false;
+
+    let white = position.side_to_move() == PieceColor::White;
+    let mut words = args.split_whitespace();
+    while let Some(word) = words.next() {
+        let mut value = || words.next().and_then(|value| value.parse::<u64>().ok());
+        match word {
+            "depth" => depth = value().map(|depth| depth as u32),
+            "movetime" => movetime = value(),
+            "wtime" if white => time_left = value(),
+            "btime" if !white => time_left = value(),
+            "winc" if white => increment = value().unwrap_or(0),
+            "binc" if !white => increment = value().unwrap_or(0),
+            "movestogo" => moves_to_go = value(),
+            "infinite" | "ponder" => infinite = true,
+            // Skip the value of the other side's clock
+            "wtime" | "btime" | "winc" | "binc" => {
+                value();
+            }
+            _ => {}
+        }
+    }
+
+    // Spend a slice of the clock on each move, keeping a margin so the flag never falls
+    let time = movetime.or_else(|| {
+        time_left.map(|time_left| {
+            let slice = time_left / moves_to_go.unwrap_or(30).max(1) + increment / 2;
+            slice.min(time_left.saturating_sub(50)).max(1)
+        })
+    });
+
+    let stop = Arc::new(AtomicBool::new(false));
+    let limits = SearchLimits {
+        depth,
+        time: if infinite {
+            None
+        } else {
+            time.map(Duration::from_millis)
+        },
+        stop: Some(stop.clone()),
+    };
+
+    let position = position.clone();
+    let thread_stop = stop.clone();
+    let handle = thread::spawn(move || {
+        let best = search(&position, &limits, &mut table.lock().unwrap(), print_info);
+
+        // An infinite search must not answer until it's told to stop
+        while infinite && !thread_stop.load(Ordering::Relaxed) {
+            thread::sleep(Duration::from_millis(5));
+        }
+
+        match best.and_then(|info| info.best_move()) {
+            Some(mv) => println!("bestmove {}", move_to_uci(mv)),
+            None => println!("bestmove 0000"),
+        }
+    });
+
+    RunningSearch { stop, handle }
+}
+
+/// Prints the perft count of every move, then the total, like other engines' `go perft`
+fn perft(position: &Position, depth: &str) {
+    let depth = depth.trim().parse().unwrap_or(1);
+    let divide = position.divide(depth);
+    for (mv, nodes) in &divide {
+        println!("{}: {}", move_to_uci(*mv), nodes);
+    }
+    println!();
+    println!(
+        "Nodes searched: {}",
+        divide.iter().map(|(_, nodes)| nodes).sum::<u64>()
+    );
+}
+
+fn print_info(info: &SearchInfo) {
+    let millis = info.elapsed.as_millis() as u64;
+    let pv: Vec<String> = info.pv.iter().map(|&mv| move_to_uci(mv)).collect();
+    println!(
+        "info depth {} score {} nodes {} time {} nps {} pv {}",
+        info.depth,
+        score_to_uci(info.score),
+        info.nodes,
+        millis,
+        info.nodes * 1000 / millis.max(1),
+        pv.join(" ")
+    );
+}
@@ -0,0 +1,175 @@
+use crate::position::PieceColor;
+
+/// A set of squares, one bit each. Square `(x, y)` is bit `x * 8 + y`, so a1 is bit 0
+/// and h8 is bit 63.
+pub type Bitboard = u64;
+
+pub fn square_bit(pos: (u8, u8)) -> Bitboard {
+    1 << (pos.0 * 8 + pos.1)
+}
+
+fn square_index(pos: (u8, u8)) -> usize {
+    (pos.0 * 8 + pos.1) as usize
+}
+
+fn index_square(index: u32) -> (u8, u8) {
+    ((index / 8) as u8, (index % 8) as u8)
+}
+
+/// The light squares, like b1 and a2
+pub const LIGHT_SQUARES: Bitboard = 0x55aa_55aa_55aa_55aa;
+
+/// Iterates over the squares in a bitboard, from a1 to h8
+pub struct Squares(pub Bitboard);
+
+impl Iterator for Squares {
+    type Item = (u8, u8);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.0 == 0 {
+            return None;
+        }
+        let index = self.0.trailing_zeros();
+        // Clear the lowest bit
+        self.0 &= self.0 - 1;
+        Some(index_square(index))
+    }
+}
+
+const KING_OFFSETS: [(i8, i8); 8] = [
+    (1, 0),
+    (1, 1),
+    (0, 1),
+    (-1, 1),
+    (-1, 0),
+    (-1, -1),
+    (0, -1),
+    (1, -1),
+];
+const KNIGHT_OFFSETS: [(i8, i8); 8] = [
+    (2, 1),
+    (1, 2),
+    (-1, 2),
+    (-2, 1),
+    (-2, -1),
+    (-1, -2),
+    (1, -2),
+    (2, -1),
+];
+
+/// Sliding directions. The first four walk towards higher bits, the last four towards lower ones.
+const DIRECTIONS: [(i8, i8); 8] = [
+    (1, 0),
+    (0, 1),
+    (1, 1),
+    (1, -1),
+    (-1, 0),
+    (0, -1),
+    (-1, -1),
+    (-1, 1),
+];
+const ROOK_DIRECTIONS: [usize; 4] = [0, 1, 4, 5];
+const BISHOP_DIRECTIONS: [usize; 4] = [2, 3, 6, 7];
+
+const fn on_board(x: i8, y: i8) -> bool {
+    x >= 0 && x < 8 && y >= 0 && y < 8
+}
+
+/// Squares one step away from each square, for pieces that don't slide
+const fn step_table(offsets: &[(i8, i8)]) -> [Bitboard; 64] {
+    let mut table = [0; 64];
+    let mut square = 0;
+    while square < 64 {
+        let x = (square / 8) as i8;
+        let y = (square % 8) as i8;
+        let mut i = 0;
+        while i < offsets.len() {
+            let (dx, dy) = offsets[i];
+            if on_board(x + dx, y + dy) {
+                table[square] |= 1 << ((x + dx) * 8 + y + dy);
+            }
+            i += 1;
+        }
+        square += 1;
+    }
+    table
+}
+
+/// Squares from each square to the edge of the board in every direction, not counting itself
+const fn ray_table() -> [[Bitboard; 64]; 8] {
+    let mut table = [[0; 64]; 8];
+    let mut direction = 0;
+    while direction < 8 {
+        let (dx, dy) = DIRECTIONS[direction];
+        let mut square = 0;
+        while square < 64 {
+            let mut x = (square / 8) as i8 + dx;
+            let mut y = (square % 8) as i8 + dy;
+            while on_board(x, y) {
+                table[direction][square] |= 1 << (x * 8 + y);
+                x += dx;
+                y += dy;
+            }
+            square += 1;
+        }
+        direction += 1;
+    }
+    table
+}
+
+static KING_ATTACKS: [Bitboard; 64] = step_table(&KING_OFFSETS);
+static KNIGHT_ATTACKS: [Bitboard; 64] = step_table(&KNIGHT_OFFSETS);
+static WHITE_PAWN_ATTACKS: [Bitboard; 64] = step_table(&[(1, -1), (1, 1)]);
+static BLACK_PAWN_ATTACKS: [Bitboard; 64] = step_table(&[(-1, -1), (-1, 1)]);
+static RAYS: [[Bitboard; 64]; 8] = ray_table();
+
+pub fn king_attacks(pos: (u8, u8)) -> Bitboard {
+    KING_ATTACKS[square_index(pos)]
+}
+
+pub fn knight_attacks(pos: (u8, u8)) -> Bitboard {
+    KNIGHT_ATTACKS[square_index(pos)]
+}
+
+/// Squares a pawn of `color` on `pos` attacks
+pub fn pawn_attacks(pos: (u8, u8), color: PieceColor) -> Bitboard {
+    match color {
+        PieceColor::White => WHITE_PAWN_ATTACKS[square_index(pos)],
+        PieceColor::Black => BLACK_PAWN_ATTACKS[square_index(pos)],
+    }
+}
+
+/// Squares a slider sees along one direction, up to and including the first piece in `occupied`
+fn ray_attacks(index: usize, direction: usize, occupied: Bitboard) -> Bitboard {
+    let ray = RAYS[direction][index];
+    let blockers = ray & occupied;
+    if blockers == 0 {
+        return ray;
+    }
+
+    // The nearest blocker is the lowest bit for rays going up, and the highest for rays going down
+    let blocker = if direction < 4 {
+        blockers.trailing_zeros()
+    } else {
+        63 - blockers.leading_zeros()
+    };
+    ray ^ RAYS[direction][blocker as usize]
+}
+
+pub fn rook_attacks(pos: (u8, u8), occupied: Bitboard) -> Bitboard {
+    let index = square_index(pos);
+    ROOK_DIRECTIONS.iter().fold(0, |attacks, &direction| {
+        attacks | ray_attacks(index, direction, occupied)
+    })
+}
+
+pub fn bishop_attacks(pos: (u8, u8), occupied: Bitboard) -> Bitboard {
+    let index = square_index(pos);
+    BISHOP_DIRECTIONS.iter().fold(0, |attacks, &direction| {
+        attacks | ray_attacks(index, direction, occupied)
+    })
+}
+
+pub fn queen_attacks(pos: (u8, u8), occupied: Bitboard) -> Bitboard {
+    rook_attacks(pos, occupied) | bishop_attacks(pos, occupied)
+}
@@ -1,11 +1,25 @@
-use crate::pieces::*;
-use bevy::{app::AppExit, prelude::*};
+use crate::{menu::*, pieces::*};
+use bevy::prelude::*;
+use bevy_chess::{
+    clock::Clock,
+    fen::FenError,
+    pgn::{pgn_date, GameRecord},
+    position::{GameOver, Move},
+};
 use bevy_mod_picking::*;
+use std::{
+    fs,
+    time::{SystemTime, UNIX_EPOCH},
+};
 
 pub struct Square {
     pub x: u8,
     pub y: u8,
     pub is_valid_move: bool,
+    /// Whether the last move went from or to here
+    pub is_last_move: bool,
+    /// Whether a king in check stands here
+    pub is_check: bool,
 }
 impl Square {
     fn is_white(&self) -> bool {
@@ -41,11 +55,71 @@ fn create_board(
                     x: i,
                     y: j,
                     is_valid_move: false,
+                    is_last_move: false,
+                    is_check: false,
                 });
         }
     }
 }
 
+/// Which side of the board the camera looks from
+#[derive(Default)]
+pub struct BoardView {
+    /// Whether Black's pieces are nearest, instead of White's
+    pub flipped: bool,
+}
+
+// Component to mark the camera looking at the board
+pub struct BoardCamera;
+
+/// Where the camera looks at the board from
+pub fn camera_transform(view: &BoardView) -> Transform {
+    let transform = Transform::from_matrix(Mat4::from_rotation_translation(
+        Quat::from_xyzw(-0.3, -0.5, -0.3, 0.5).normalize(),
+        Vec3::new(-7.0, 20.0, 4.0),
+    ));
+    if !view.flipped {
+        return transform;
+    }
+
+    // Half a turn around the middle of the board
+    let center = Vec3::new(3.5, 0., 3.5);
+    let half_turn = Quat::from_rotation_y(std::f32::consts::PI);
+    Transform {
+        translation: center + half_turn * (transform.translation - center),
+        rotation: half_turn * transform.rotation,
+        ..transform
+    }
+}
+
+fn orient_camera(view: ChangedRes<BoardView>, mut query: Query<&mut Transform, With<BoardCamera>>) {
+    for mut transform in query.iter_mut() {
+        *transform = camera_transform(&view);
+    }
+}
+
+/// Look from the side of the only human player, if just one side is human
+fn follow_human_player(players: ChangedRes<Players>, mut view: ResMut<BoardView>) {
+    let flipped = match (players.white, players.black) {
+        (PlayerKind::Human, PlayerKind::Human) => return,
+        (_, PlayerKind::Human) => true,
+        (PlayerKind::Human, _) => false,
+        _ => return,
+    };
+    if view.flipped != flipped {
+        view.flipped = flipped;
+    }
+}
+
+/// Turn the board around when V is pressed
+fn flip_board_key(keyboard_input: Res<Input<KeyCode>>, mut view: ResMut<BoardView>) {
+    if keyboard_input.just_pressed(KeyCode::V) {
+        view.flipped = !view.flipped;
+    }
+}
+
+/// Highlights take precedence in this order: the square under the cursor, the selected square,
+/// the selected piece's moves, a king in check, then the last move
 fn color_squares(
     pick_state: Res<PickState>,
     selected_square: Res<SelectedSquare>,
@@ -67,6 +141,10 @@ fn color_squares(
             materials.selected_color.clone()
         } else if square.is_valid_move {
             materials.move_color.clone()
+        } else if square.is_check {
+            materials.check_color.clone()
+        } else if square.is_last_move {
+            materials.last_move_color.clone()
         } else if square.is_white() {
             materials.white_color.clone()
         } else {
@@ -75,12 +153,52 @@ fn color_squares(
     }
 }
 
+/// Mark the last move's squares and a king in check, in whichever position is on show
+fn mark_squares(
+    position: Res<Position>,
+    record: Res<GameRecord>,
+    upcoming_moves: Res<UpcomingMoves>,
+    history_view: Res<HistoryView>,
+    mut query: Query<&mut Square>,
+) {
+    let viewed;
+    let position = match history_view.ply {
+        Some(ply) => {
+            viewed = viewed_position(&record, &upcoming_moves, ply);
+            &viewed
+        }
+        None => &*position,
+    };
+
+    let last_move = position.last_move();
+    let side_to_move = position.side_to_move();
+    let check_square = if position.is_in_check(side_to_move) {
+        position.king_square(side_to_move)
+    } else {
+        None
+    };
+
+    for mut square in query.iter_mut() {
+        let square_pos = (square.x, square.y);
+        let is_last_move = last_move.is_some_and(|mv| mv.from == square_pos || mv.to == square_pos);
+        let is_check = check_square == Some(square_pos);
+
+        // Only touch the squares that change, so they aren't marked as mutated every frame
+        if square.is_last_move != is_last_move || square.is_check != is_check {
+            square.is_last_move = is_last_move;
+            square.is_check = is_check;
+        }
+    }
+}
+
 struct SquareMaterials {
     highlight_color: Handle<StandardMaterial>,
     selected_color: Handle<StandardMaterial>,
     black_color: Handle<StandardMaterial>,
     white_color: Handle<StandardMaterial>,
     move_color: Handle<StandardMaterial>,
+    last_move_color: Handle<StandardMaterial>,
+    check_color: Handle<StandardMaterial>,
 }
 
 impl FromResources for SquareMaterials {
@@ -92,6 +210,8 @@ impl FromResources for SquareMaterials {
             black_color: materials.add(Color::rgb(0., 0.1, 0.1).into()),
             white_color: materials.add(Color::rgb(1., 0.9, 0.9).into()),
             move_color: materials.add(Color::rgb(0.1, 0.1, 0.9).into()),
+            last_move_color: materials.add(Color::rgb(0.7, 0.7, 0.2).into()),
+            check_color: materials.add(Color::rgb(1., 0.5, 0.).into()),
         }
     }
 }
@@ -105,9 +225,11 @@ struct SelectedPiece {
     entity: Option<Entity>,
 }
 pub struct PlayerTurn(pub PieceColor);
-impl Default for PlayerTurn {
-    fn default() -> Self {
-        Self(PieceColor::White)
+impl FromResources for PlayerTurn {
+    fn from_resources(resources: &Resources) -> Self {
+        // Start with whoever the position says is to move
+        let position = resources.get::<Position>().unwrap();
+        Self(position.side_to_move())
     }
 }
 impl PlayerTurn {
@@ -116,15 +238,94 @@ impl PlayerTurn {
     }
 }
 
+/// Whether pawns are promoted to queens straight away instead of asking the player.
+/// Add this resource before `BoardPlugin` to change it.
+#[derive(Default)]
+pub struct PromotionOptions {
+    pub auto_queen: bool,
+}
+
+/// Promotion move waiting for the player to choose a piece
+#[derive(Default)]
+pub struct PendingPromotion {
+    pub mv: Option<Move>,
+}
+
+/// Who makes the moves for one side
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PlayerKind {
+    Human,
+    /// The built-in engine
+    Engine,
+    /// An external engine speaking UCI
+    Uci,
+}
+
+/// Who plays each side. Add this resource before `BoardPlugin` to change it.
+pub struct Players {
+    pub white: PlayerKind,
+    pub black: PlayerKind,
+}
+impl Default for Players {
+    fn default() -> Self {
+        Self {
+            white: PlayerKind::Human,
+            black: PlayerKind::Human,
+        }
+    }
+}
+impl Players {
+    pub fn get(&self, color: PieceColor) -> PlayerKind {
+        match color {
+            PieceColor::White => self.white,
+            PieceColor::Black => self.black,
+        }
+    }
+
+    /// How many moves to take back or replay at once, so that a human is left to move when one
+    /// plays against an engine. `next` is who would be to move after a single move.
+    fn plies_to_human(&self, next: PieceColor) -> usize {
+        let human_playing = self.white == PlayerKind::Human || self.black == PlayerKind::Human;
+        if human_playing && self.get(next) != PlayerKind::Human {
+            2
+        } else {
+            1
+        }
+    }
+}
+
+/// Chess clock, if the game is played with a time control.
+/// Add this resource before `BoardPlugin` to set one.
+#[derive(Default)]
+pub struct GameClock {
+    pub clock: Option<Clock>,
+}
+
+/// Holds the result once the game has finished
+#[derive(Default)]
+pub struct GameStatus {
+    pub game_over: Option<GameOver>,
+}
+
 fn select_square(
     pick_state: Res<PickState>,
     mouse_button_inputs: Res<Input<MouseButton>>,
     mut selected_square: ResMut<SelectedSquare>,
     mut selected_piece: ResMut<SelectedPiece>,
+    status: Res<GameStatus>,
+    pending_promotion: Res<PendingPromotion>,
+    turn: Res<PlayerTurn>,
+    players: Res<Players>,
+    history_view: Res<HistoryView>,
     squares_query: Query<&Square>,
 ) {
-    // Only run if the left button is pressed
-    if !mouse_button_inputs.just_pressed(MouseButton::Left) {
+    // Only run if the left button is pressed and the game is waiting for a human's move
+    if !mouse_button_inputs.just_pressed(MouseButton::Left)
+        || status.game_over.is_some()
+        || pending_promotion.mv.is_some()
+        || players.get(turn.0) != PlayerKind::Human
+        || history_view.ply.is_some()
+    {
         return;
     }
 
@@ -146,6 +347,7 @@ fn select_piece(
     selected_square: ChangedRes<SelectedSquare>,
     mut selected_piece: ResMut<SelectedPiece>,
     turn: Res<PlayerTurn>,
+    position: Res<Position>,
     mut squares_query: Query<&mut Square>,
     pieces_query: Query<(Entity, &Piece)>,
 ) {
@@ -174,15 +376,15 @@ fn select_piece(
 
     if let Some(selected_piece_entity) = selected_piece.entity {
         // Mark all valid movement squares for the currently selected piece.
-        let pieces_vec: Vec<Piece> = pieces_query.iter().map(|(_, piece)| *piece).collect();
         let piece = if let Ok((_piece_entity, piece)) = pieces_query.get(selected_piece_entity) {
             piece
         } else {
             return;
         };
+        let moves = position.legal_moves_from((piece.x, piece.y));
 
         for mut square in squares_query.iter_mut() {
-            square.is_valid_move = piece.is_move_valid((square.x, square.y), pieces_vec.clone());
+            square.is_valid_move = moves.iter().any(|m| m.to == (square.x, square.y));
         }
     } else {
         // Reset all squares back to default state.
@@ -193,12 +395,14 @@ fn select_piece(
 }
 
 fn move_piece(
-    commands: &mut Commands,
     selected_square: ChangedRes<SelectedSquare>,
     mut selected_piece: ResMut<SelectedPiece>,
-    mut turn: ResMut<PlayerTurn>,
+    position: Res<Position>,
+    promotion_options: Res<PromotionOptions>,
+    mut pending_promotion: ResMut<PendingPromotion>,
     squares_query: Query<&Square>,
-    mut pieces_query: Query<(Entity, &mut Piece)>,
+    pieces_query: Query<&Piece>,
+    mut make_move_events: ResMut<Events<MakeMoveEvent>>,
     mut reset_selected_square_event: ResMut<Events<ResetSelectedSquareEvent>>,
 ) {
     let square_entity = if let Some(entity) = selected_square.entity {
@@ -214,40 +418,436 @@ fn move_piece(
     };
 
     if let Some(selected_piece_entity) = selected_piece.entity {
+        let piece = if let Ok(piece) = pieces_query.get(selected_piece_entity) {
+            piece
+        } else {
+            return;
+        };
+
+        // Promotions give several moves to the same square, ordered best first
+        let legal_move = position
+            .legal_moves_from((piece.x, piece.y))
+            .into_iter()
+            .find(|m| m.to == (square.x, square.y));
+
+        if let Some(legal_move) = legal_move {
+            if legal_move.promotion.is_some() && !promotion_options.auto_queen {
+                // Wait for the player to pick the new piece
+                pending_promotion.mv = Some(legal_move);
+            } else {
+                make_move_events.send(MakeMoveEvent(legal_move));
+            }
+        }
+
+        selected_piece.entity = None;
+        reset_selected_square_event.send(ResetSelectedSquareEvent);
+    }
+}
+
+/// Whether pieces can also be dragged to their new square, instead of only clicked there.
+/// Add this resource before `BoardPlugin` to change it.
+#[derive(Default)]
+pub struct DragOptions {
+    pub enabled: bool,
+}
+
+/// Piece following the cursor, if one is being dragged
+#[derive(Default)]
+struct DraggedPiece {
+    entity: Option<Entity>,
+}
+
+/// How high a dragged piece is lifted above the board
+const DRAG_HEIGHT: f32 = 0.5;
+
+/// Lift the selected piece when the button is held down on it
+fn start_drag(
+    commands: &mut Commands,
+    drag_options: Res<DragOptions>,
+    mouse_button_inputs: Res<Input<MouseButton>>,
+    pick_state: Res<PickState>,
+    selected_piece: Res<SelectedPiece>,
+    mut dragged_piece: ResMut<DraggedPiece>,
+    squares_query: Query<&Square>,
+    pieces_query: Query<&Piece>,
+) {
+    if !drag_options.enabled
+        || !mouse_button_inputs.pressed(MouseButton::Left)
+        || dragged_piece.entity.is_some()
+    {
+        return;
+    }
+
+    let piece_entity = if let Some(entity) = selected_piece.entity {
+        entity
+    } else {
+        return;
+    };
+    let piece = if let Ok(piece) = pieces_query.get(piece_entity) {
+        piece
+    } else {
+        return;
+    };
+
+    // Only pick it up from its own square, so clicking where it should go still moves it
+    let square_under_cursor = pick_state
+        .top(Group::default())
+        .and_then(|(entity, _intersection)| squares_query.get(*entity).ok());
+    if square_under_cursor.is_some_and(|square| (square.x, square.y) == (piece.x, piece.y)) {
+        dragged_piece.entity = Some(piece_entity);
+        commands.insert_one(piece_entity, Dragged);
+    }
+}
+
+/// Keep the dragged piece under the cursor while it's over the board
+fn drag_piece(pick_state: Res<PickState>, mut query: Query<&mut Transform, With<Dragged>>) {
+    let cursor_position = if let Some((_entity, intersection)) = pick_state.top(Group::default()) {
+        *intersection.position()
+    } else {
+        return;
+    };
+
+    for mut transform in query.iter_mut() {
+        transform.translation = cursor_position + Vec3::new(0., DRAG_HEIGHT, 0.);
+    }
+}
+
+/// Drop the dragged piece on the square under the cursor, which plays the move like a click
+/// there would. Illegal drops put the piece straight back.
+fn drop_piece(
+    commands: &mut Commands,
+    mouse_button_inputs: Res<Input<MouseButton>>,
+    pick_state: Res<PickState>,
+    position: Res<Position>,
+    mut dragged_piece: ResMut<DraggedPiece>,
+    mut selected_square: ResMut<SelectedSquare>,
+    squares_query: Query<&Square>,
+    mut pieces_query: Query<(&Piece, &mut Transform)>,
+) {
+    if !mouse_button_inputs.just_released(MouseButton::Left) {
+        return;
+    }
+    let piece_entity = if let Some(entity) = dragged_piece.entity.take() {
+        entity
+    } else {
+        return;
+    };
+    // The piece may have gone, like when a new game started mid-drag
+    let (piece, mut transform) = if let Ok(piece) = pieces_query.get_mut(piece_entity) {
+        piece
+    } else {
+        return;
+    };
+    commands.remove_one::<Dragged>(piece_entity);
+
+    let target = pick_state
+        .top(Group::default())
+        .and_then(|(entity, _intersection)| {
+            squares_query
+                .get(*entity)
+                .ok()
+                .map(|square| (*entity, (square.x, square.y)))
+        });
+    let is_legal = target.is_some_and(|(_entity, to)| {
+        position
+            .legal_moves_from((piece.x, piece.y))
+            .iter()
+            .any(|m| m.to == to)
+    });
+    if !is_legal {
+        transform.translation = Vec3::new(piece.x as f32, 0., piece.y as f32);
+    }
+
+    // Letting go over the piece's own square keeps it selected, for click-click moves
+    if let Some((square_entity, to)) = target {
+        if to != (piece.x, piece.y) {
+            selected_square.entity = Some(square_entity);
+        }
+    }
+}
+
+/// Plays a move on the board, whether it came from a click or elsewhere
+pub struct MakeMoveEvent(pub Move);
+
+fn make_move(
+    commands: &mut Commands,
+    mut event_reader: Local<EventReader<MakeMoveEvent>>,
+    events: Res<Events<MakeMoveEvent>>,
+    mut turn: ResMut<PlayerTurn>,
+    mut position: ResMut<Position>,
+    mut record: ResMut<GameRecord>,
+    mut upcoming_moves: ResMut<UpcomingMoves>,
+    mut game_clock: ResMut<GameClock>,
+    mut pieces_query: Query<(Entity, &mut Piece)>,
+) {
+    for MakeMoveEvent(legal_move) in event_reader.iter(&events) {
+        let legal_move = *legal_move;
+        if !position.legal_moves().contains(&legal_move) {
+            continue;
+        }
+
+        // Stepping forward keeps the rest of the line, anything else replaces it
+        if upcoming_moves.moves.last() == Some(&legal_move) {
+            upcoming_moves.moves.pop();
+        } else {
+            upcoming_moves.moves.clear();
+        }
+
         let pieces_entity_vec = pieces_query
             .iter_mut()
             .map(|(entity, piece)| (entity, *piece))
             .collect::<Vec<(Entity, Piece)>>();
-        // Move the selected piece to the selected square
-        let mut piece =
-            if let Ok((_piece_entity, piece)) = pieces_query.get_mut(selected_piece_entity) {
-                piece
-            } else {
-                return;
-            };
 
-        if square.is_valid_move {
-            // Check if a piece of the opposite color exists in this square and despawn it
-            for (other_entity, other_piece) in pieces_entity_vec {
-                if other_piece.x == square.x
-                    && other_piece.y == square.y
-                    && other_piece.color != piece.color
-                {
+        // Check if the move captures a piece and despawn it
+        if let Some(capture_square) = position.capture_square(legal_move) {
+            for (other_entity, other_piece) in pieces_entity_vec.iter() {
+                if (other_piece.x, other_piece.y) == capture_square {
                     // Mark the piece as taken
-                    commands.insert_one(other_entity, Taken);
+                    commands.insert_one(*other_entity, Taken);
                 }
             }
+        }
 
-            // Move piece
-            piece.x = square.x;
-            piece.y = square.y;
+        // When castling, the rook jumps over the king
+        let mut relocations = vec![(legal_move.from, legal_move.to)];
+        relocations.extend(position.castling_rook_move(legal_move));
+
+        // Move pieces, promoting the moved one if needed
+        for (from, to) in relocations {
+            for (entity, other_piece) in pieces_entity_vec.iter() {
+                if (other_piece.x, other_piece.y) != from {
+                    continue;
+                }
+
+                if let Ok((_entity, mut piece)) = pieces_query.get_mut(*entity) {
+                    piece.x = to.0;
+                    piece.y = to.1;
+
+                    if from == legal_move.from {
+                        if let Some(promotion) = legal_move.promotion {
+                            piece.piece_type = promotion;
+                            commands.insert_one(*entity, Promoted);
+                        }
+                    }
+                }
+            }
+        }
+
+        position.make_move(legal_move);
+        record.moves.push(legal_move);
+        if let Some(clock) = &mut game_clock.clock {
+            clock.press();
+        }
+
+        // Change turn
+        turn.change();
+    }
+}
+
+/// Moves that can be stepped forward through, with the next one last.
+/// Filled with the moves of a loaded game, or with moves that were taken back.
+#[derive(Default)]
+pub struct UpcomingMoves {
+    pub moves: Vec<Move>,
+}
+
+/// Every move of the game: the ones played so far, then the upcoming ones
+pub fn game_line(record: &GameRecord, upcoming_moves: &UpcomingMoves) -> Vec<Move> {
+    let mut line = record.moves.clone();
+    line.extend(upcoming_moves.moves.iter().rev());
+    line
+}
+
+/// Earlier position shown instead of the live game, as the number of moves into the game line.
+/// The board can't be played on meanwhile, but the clocks and engines carry on in the live game.
+#[derive(Default)]
+pub struct HistoryView {
+    pub ply: Option<usize>,
+}
+
+/// Swap the pieces for stand-ins showing the viewed position, or back again
+fn show_history_view(
+    commands: &mut Commands,
+    history_view: ChangedRes<HistoryView>,
+    piece_data: Res<PieceData>,
+    record: Res<GameRecord>,
+    upcoming_moves: Res<UpcomingMoves>,
+    history_pieces_query: Query<Entity, With<HistoryPiece>>,
+    pieces_query: Query<&Children, With<Piece>>,
+    mut visible_query: Query<&mut Visible>,
+) {
+    for entity in history_pieces_query.iter() {
+        commands.despawn_recursive(entity);
+    }
+
+    // The live pieces stay where they are, just out of sight
+    for children in pieces_query.iter() {
+        for child in children.iter() {
+            if let Ok(mut visible) = visible_query.get_mut(*child) {
+                visible.is_visible = history_view.ply.is_none();
+            }
+        }
+    }
+
+    if let Some(ply) = history_view.ply {
+        let position = viewed_position(&record, &upcoming_moves, ply);
+        spawn_history_pieces(commands, &piece_data, &position);
+    }
+}
+
+/// The position `ply` moves into the game line
+fn viewed_position(record: &GameRecord, upcoming_moves: &UpcomingMoves, ply: usize) -> Position {
+    let mut position = record.start.clone();
+    for mv in game_line(record, upcoming_moves).into_iter().take(ply) {
+        position.make_move(mv);
+    }
+    position
+}
+
+/// Taking back or replaying a move goes back to the live game. Other moves, like the engine's,
+/// only change the live game, unless they replace the taken back moves being looked at.
+fn leave_history_view(
+    mut make_move_reader: Local<EventReader<MakeMoveEvent>>,
+    make_move_events: Res<Events<MakeMoveEvent>>,
+    mut unmake_move_reader: Local<EventReader<UnmakeMoveEvent>>,
+    unmake_move_events: Res<Events<UnmakeMoveEvent>>,
+    mut redo_move_reader: Local<EventReader<RedoMoveEvent>>,
+    redo_move_events: Res<Events<RedoMoveEvent>>,
+    record: Res<GameRecord>,
+    mut history_view: ResMut<HistoryView>,
+) {
+    // Read every queue, so old events don't turn up later
+    let played = make_move_reader.iter(&make_move_events).next().is_some();
+    let stepped = unmake_move_reader
+        .iter(&unmake_move_events)
+        .next()
+        .is_some()
+        | redo_move_reader.iter(&redo_move_events).next().is_some();
+
+    // The live position is never viewed as history, so a viewed ply this far in was taken back
+    let replaced = played && matches!(history_view.ply, Some(ply) if ply >= record.moves.len());
+    if (stepped || replaced) && history_view.ply.is_some() {
+        history_view.ply = None;
+    }
+}
+
+/// Takes back the last move played on the board, or the last two against an engine
+pub struct UnmakeMoveEvent;
+
+fn unmake_move(
+    commands: &mut Commands,
+    mut event_reader: Local<EventReader<UnmakeMoveEvent>>,
+    events: Res<Events<UnmakeMoveEvent>>,
+    piece_data: Res<PieceData>,
+    mut turn: ResMut<PlayerTurn>,
+    mut position: ResMut<Position>,
+    mut record: ResMut<GameRecord>,
+    mut upcoming_moves: ResMut<UpcomingMoves>,
+    mut status: ResMut<GameStatus>,
+    mut pending_promotion: ResMut<PendingPromotion>,
+    mut game_clock: ResMut<GameClock>,
+    players: Res<Players>,
+    mut selected_square: ResMut<SelectedSquare>,
+    mut selected_piece: ResMut<SelectedPiece>,
+    mut squares_query: Query<&mut Square>,
+    pieces_query: Query<Entity, With<Piece>>,
+) {
+    let mut taken_back = false;
+    for _event in event_reader.iter(&events) {
+        // Undoing while choosing a promotion only cancels the promotion
+        if pending_promotion.mv.take().is_some() {
+            continue;
+        }
+
+        // Against an engine, go back to the human's turn. If the game doesn't go back that far,
+        // there's nothing of theirs to take back.
+        let plies = players.plies_to_human(turn.0.opposite());
+        if plies > record.moves.len() {
+            continue;
+        }
+
+        for _ in 0..plies {
+            let last_move = if let Some(last_move) = position.unmake_move() {
+                last_move
+            } else {
+                break;
+            };
+            record.moves.pop();
+            upcoming_moves.moves.push(last_move);
+            if let Some(clock) = &mut game_clock.clock {
+                clock.take_back();
+            }
+
+            // A finished game can be picked up again from here
+            if status.game_over.take().is_some() {
+                record.set_result(None);
+            }
 
-            // Change turn
             turn.change();
+            taken_back = true;
         }
+    }
 
+    // Pieces brought back by an earlier takeback aren't spawned yet, so moving the entities
+    // move by move can't find them. Set the board up again from the position instead, once
+    // for all of this frame's takebacks.
+    if taken_back {
+        for entity in pieces_query.iter() {
+            commands.despawn_recursive(entity);
+        }
+        spawn_pieces(commands, &piece_data, &position);
+    }
+
+    // Whatever was selected may not be there anymore
+    if selected_piece.entity.is_some() || selected_square.entity.is_some() {
         selected_piece.entity = None;
-        reset_selected_square_event.send(ResetSelectedSquareEvent);
+        selected_square.entity = None;
+        for mut square in squares_query.iter_mut() {
+            square.is_valid_move = false;
+        }
+    }
+}
+
+/// Plays the next of the `UpcomingMoves` again, or the next two against an engine
+pub struct RedoMoveEvent;
+
+fn redo_move(
+    mut event_reader: Local<EventReader<RedoMoveEvent>>,
+    events: Res<Events<RedoMoveEvent>>,
+    turn: Res<PlayerTurn>,
+    players: Res<Players>,
+    upcoming_moves: Res<UpcomingMoves>,
+    mut make_move_events: ResMut<Events<MakeMoveEvent>>,
+) {
+    // Only one redo can be done per frame, since the moves are consumed when played
+    if event_reader.iter(&events).next().is_some() {
+        let plies = players.plies_to_human(turn.0.opposite());
+        for &next_move in upcoming_moves.moves.iter().rev().take(plies) {
+            make_move_events.send(MakeMoveEvent(next_move));
+        }
+    }
+}
+
+/// Undo with Ctrl+Z or the left arrow, and redo with Ctrl+Y, Ctrl+Shift+Z or the right arrow
+fn undo_redo_keys(
+    keyboard_input: Res<Input<KeyCode>>,
+    mut unmake_move_events: ResMut<Events<UnmakeMoveEvent>>,
+    mut redo_move_events: ResMut<Events<RedoMoveEvent>>,
+) {
+    let ctrl =
+        keyboard_input.pressed(KeyCode::LControl) || keyboard_input.pressed(KeyCode::RControl);
+    let shift = keyboard_input.pressed(KeyCode::LShift) || keyboard_input.pressed(KeyCode::RShift);
+
+    if keyboard_input.just_pressed(KeyCode::Left)
+        || (ctrl && !shift && keyboard_input.just_pressed(KeyCode::Z))
+    {
+        unmake_move_events.send(UnmakeMoveEvent);
+    }
+    if keyboard_input.just_pressed(KeyCode::Right)
+        || (ctrl && keyboard_input.just_pressed(KeyCode::Y))
+        || (ctrl && shift && keyboard_input.just_pressed(KeyCode::Z))
+    {
+        redo_move_events.send(RedoMoveEvent);
     }
 }
 
@@ -263,19 +863,223 @@ fn reset_selected_square(
     }
 }
 
-struct Taken;
-fn despawn_taken_pieces(
-    commands: &mut Commands,
-    mut app_exit_events: ResMut<Events<AppExit>>,
-    query: Query<(Entity, &Piece, &Taken)>,
+/// Checks for checkmate or stalemate whenever the position changes
+fn check_game_over(
+    position: ChangedRes<Position>,
+    mut status: ResMut<GameStatus>,
+    mut record: ResMut<GameRecord>,
+    mut game_over_events: ResMut<Events<GameOver>>,
 ) {
-    for (entity, piece, _taken) in query.iter() {
-        // If the king is taken, we should exit
-        if piece.piece_type == PieceType::King {
-            println!("{} won! Thanks for playing!", piece.color.opposite());
-            app_exit_events.send(AppExit);
+    if status.game_over.is_some() {
+        return;
+    }
+
+    if let Some(game_over) = position.game_over() {
+        end_game(game_over, &mut status, &mut record, &mut game_over_events);
+    }
+}
+
+fn end_game(
+    game_over: GameOver,
+    status: &mut GameStatus,
+    record: &mut GameRecord,
+    game_over_events: &mut Events<GameOver>,
+) {
+    status.game_over = Some(game_over);
+    record.set_result(Some(game_over.result));
+    game_over_events.send(game_over);
+}
+
+/// Runs the side to move's clock, ending the game when it runs out
+fn tick_clock(
+    time: Res<Time>,
+    position: Res<Position>,
+    mut game_clock: ResMut<GameClock>,
+    mut status: ResMut<GameStatus>,
+    mut record: ResMut<GameRecord>,
+    mut game_over_events: ResMut<Events<GameOver>>,
+) {
+    if status.game_over.is_some() {
+        return;
+    }
+    let clock = match &mut game_clock.clock {
+        Some(clock) => clock,
+        None => return,
+    };
+
+    clock.tick(time.delta());
+    if let Some(flagged) = clock.flagged() {
+        end_game(
+            position.timeout(flagged),
+            &mut status,
+            &mut record,
+            &mut game_over_events,
+        );
+    }
+}
+
+/// Replaces the game on the board with another one
+pub struct NewGameEvent {
+    /// Game to set up. Its moves are left to step through with redo.
+    pub record: GameRecord,
+    /// Whether the players change sides, like in a rematch
+    pub swap_colors: bool,
+}
+
+impl NewGameEvent {
+    /// A fresh game from `start`, keeping the time control of `previous`
+    pub fn from_start(start: Position, previous: &GameRecord) -> Self {
+        let mut record = GameRecord::new(start);
+        set_new_game_tags(&mut record);
+        if let Some(time_control) = previous.tag("TimeControl") {
+            record.set_tag("TimeControl", time_control);
+        }
+        Self {
+            record,
+            swap_colors: false,
         }
+    }
+
+    /// A fresh game from the position in `fen`, keeping the time control of `previous`
+    pub fn from_fen(fen: &str, previous: &GameRecord) -> Result<Self, FenError> {
+        Ok(Self::from_start(Position::from_fen(fen)?, previous))
+    }
+
+    /// The same game again from the start, with the players swapping sides
+    pub fn rematch(previous: &GameRecord) -> Self {
+        Self {
+            swap_colors: true,
+            ..Self::from_start(previous.start.clone(), previous)
+        }
+    }
+}
+
+/// How new games from the menu start. Add this resource before `BoardPlugin` to change it.
+#[derive(Default)]
+pub struct NewGameOptions {
+    /// Starting position as FEN, instead of the standard one
+    pub fen: Option<String>,
+}
 
+/// Fills in the tags of a game that starts now
+pub fn set_new_game_tags(record: &mut GameRecord) {
+    record.set_tag("Site", "Bevy Chess");
+    if let Ok(now) = SystemTime::now().duration_since(UNIX_EPOCH) {
+        record.set_tag("Date", &pgn_date(now.as_secs()));
+    }
+}
+
+fn new_game(
+    commands: &mut Commands,
+    mut event_reader: Local<EventReader<NewGameEvent>>,
+    events: Res<Events<NewGameEvent>>,
+    piece_data: Res<PieceData>,
+    mut turn: ResMut<PlayerTurn>,
+    mut position: ResMut<Position>,
+    mut record: ResMut<GameRecord>,
+    mut upcoming_moves: ResMut<UpcomingMoves>,
+    mut status: ResMut<GameStatus>,
+    mut game_clock: ResMut<GameClock>,
+    mut players: ResMut<Players>,
+    pieces_query: Query<Entity, With<Piece>>,
+) {
+    // Only the latest request matters if several came in together
+    let event = if let Some(event) = event_reader.iter(&events).last() {
+        event
+    } else {
+        return;
+    };
+
+    for entity in pieces_query.iter() {
+        commands.despawn_recursive(entity);
+    }
+
+    *record = event.record.clone();
+    let mut moves = std::mem::take(&mut record.moves);
+    moves.reverse();
+    upcoming_moves.moves = moves;
+
+    *position = record.start.clone();
+    spawn_pieces(commands, &piece_data, &position);
+
+    turn.0 = position.side_to_move();
+    status.game_over = None;
+    if let Some(clock) = &mut game_clock.clock {
+        *clock = Clock::new(clock.time_control(), position.side_to_move());
+    }
+    if event.swap_colors {
+        std::mem::swap(&mut players.white, &mut players.black);
+    }
+}
+
+/// Drops whatever was selected or viewed in the previous game
+fn reset_selection_on_new_game(
+    mut event_reader: Local<EventReader<NewGameEvent>>,
+    events: Res<Events<NewGameEvent>>,
+    mut selected_square: ResMut<SelectedSquare>,
+    mut selected_piece: ResMut<SelectedPiece>,
+    mut pending_promotion: ResMut<PendingPromotion>,
+    mut history_view: ResMut<HistoryView>,
+    mut squares_query: Query<&mut Square>,
+) {
+    if event_reader.iter(&events).next().is_none() {
+        return;
+    }
+
+    selected_square.entity = None;
+    selected_piece.entity = None;
+    pending_promotion.mv = None;
+    history_view.ply = None;
+    for mut square in squares_query.iter_mut() {
+        square.is_valid_move = false;
+    }
+}
+
+/// Where positions are exported to and loaded from
+pub const FEN_FILE: &str = "position.fen";
+
+/// Export the current position as FEN when F is pressed, to a file and to the clipboard
+fn export_fen(keyboard_input: Res<Input<KeyCode>>, position: Res<Position>) {
+    if !keyboard_input.just_pressed(KeyCode::F) {
+        return;
+    }
+
+    let fen = position.to_fen();
+    match fs::write(FEN_FILE, format!("{}\n", fen)) {
+        Ok(()) => println!("Saved {} to {}", fen, FEN_FILE),
+        Err(err) => eprintln!("Couldn't save {}: {}", FEN_FILE, err),
+    }
+    match arboard::Clipboard::new().and_then(|mut clipboard| clipboard.set_text(fen)) {
+        Ok(()) => println!("Copied the position to the clipboard"),
+        Err(err) => eprintln!("Couldn't copy the position to the clipboard: {}", err),
+    }
+}
+
+/// Reads a position pasted to the clipboard from elsewhere
+pub fn paste_fen() -> Result<String, String> {
+    arboard::Clipboard::new()
+        .and_then(|mut clipboard| clipboard.get_text())
+        .map_err(|err| format!("Couldn't read the clipboard: {}", err))
+}
+
+/// Where games are saved to and loaded from
+pub const SAVE_FILE: &str = "game.pgn";
+
+/// Save the game so far as PGN when P is pressed
+fn save_pgn(keyboard_input: Res<Input<KeyCode>>, record: Res<GameRecord>) {
+    if !keyboard_input.just_pressed(KeyCode::P) {
+        return;
+    }
+
+    match fs::write(SAVE_FILE, record.to_pgn()) {
+        Ok(()) => println!("Saved game to {}", SAVE_FILE),
+        Err(err) => eprintln!("Couldn't save {}: {}", SAVE_FILE, err),
+    }
+}
+
+struct Taken;
+fn despawn_taken_pieces(commands: &mut Commands, query: Query<(Entity, &Taken)>) {
+    for (entity, _taken) in query.iter() {
         // Despawn piece and children
         commands.despawn_recursive(entity);
     }
@@ -287,14 +1091,84 @@ impl Plugin for BoardPlugin {
         app.init_resource::<SelectedSquare>()
             .init_resource::<SelectedPiece>()
             .init_resource::<SquareMaterials>()
+            .init_resource::<Position>()
             .init_resource::<PlayerTurn>()
+            .init_resource::<GameRecord>()
+            .init_resource::<UpcomingMoves>()
+            .init_resource::<GameStatus>()
+            .init_resource::<PromotionOptions>()
+            .init_resource::<PendingPromotion>()
+            .init_resource::<Players>()
+            .init_resource::<GameClock>()
+            .init_resource::<NewGameOptions>()
+            .init_resource::<HistoryView>()
+            .init_resource::<BoardView>()
+            .init_resource::<DragOptions>()
+            .init_resource::<DraggedPiece>()
             .add_event::<ResetSelectedSquareEvent>()
+            .add_event::<MakeMoveEvent>()
+            .add_event::<UnmakeMoveEvent>()
+            .add_event::<RedoMoveEvent>()
+            .add_event::<GameOver>()
+            .add_event::<NewGameEvent>()
             .add_startup_system(create_board.system())
-            .add_system(color_squares.system())
-            .add_system(select_square.system())
-            .add_system(move_piece.system())
-            .add_system(select_piece.system())
-            .add_system(despawn_taken_pieces.system())
-            .add_system(reset_selected_square.system());
+            // New games are started from the menus, so this one runs on every screen
+            .add_system(new_game.system())
+            .on_state_update(
+                APP_STATE_STAGE,
+                AppState::Playing,
+                reset_selection_on_new_game.system(),
+            )
+            .on_state_update(
+                APP_STATE_STAGE,
+                AppState::Playing,
+                show_history_view.system(),
+            )
+            .on_state_update(
+                APP_STATE_STAGE,
+                AppState::Playing,
+                leave_history_view.system(),
+            )
+            .on_state_update(
+                APP_STATE_STAGE,
+                AppState::Playing,
+                follow_human_player.system(),
+            )
+            .on_state_update(APP_STATE_STAGE, AppState::Playing, flip_board_key.system())
+            .on_state_update(APP_STATE_STAGE, AppState::Playing, orient_camera.system())
+            .on_state_update(APP_STATE_STAGE, AppState::Playing, save_pgn.system())
+            .on_state_update(APP_STATE_STAGE, AppState::Playing, mark_squares.system())
+            .on_state_update(APP_STATE_STAGE, AppState::Playing, export_fen.system())
+            .on_state_update(APP_STATE_STAGE, AppState::Playing, color_squares.system())
+            .on_state_update(APP_STATE_STAGE, AppState::Playing, select_square.system())
+            // Drops change the selected square, so they come before the systems reacting to it
+            .on_state_update(APP_STATE_STAGE, AppState::Playing, drop_piece.system())
+            .on_state_update(APP_STATE_STAGE, AppState::Playing, move_piece.system())
+            .on_state_update(APP_STATE_STAGE, AppState::Playing, make_move.system())
+            .on_state_update(APP_STATE_STAGE, AppState::Playing, unmake_move.system())
+            .on_state_update(APP_STATE_STAGE, AppState::Playing, redo_move.system())
+            .on_state_update(APP_STATE_STAGE, AppState::Playing, undo_redo_keys.system())
+            .on_state_update(APP_STATE_STAGE, AppState::Playing, select_piece.system())
+            // Picks up the piece just selected
+            .on_state_update(APP_STATE_STAGE, AppState::Playing, start_drag.system())
+            .on_state_update(APP_STATE_STAGE, AppState::Playing, drag_piece.system())
+            .on_state_update(APP_STATE_STAGE, AppState::Playing, check_game_over.system())
+            .on_state_update(APP_STATE_STAGE, AppState::Playing, tick_clock.system())
+            .on_state_update(
+                APP_STATE_STAGE,
+                AppState::Playing,
+                despawn_taken_pieces.system(),
+            )
+            .on_state_update(
+                APP_STATE_STAGE,
+                AppState::Playing,
+                reset_selected_square.system(),
+            )
+            // Let the last capture finish on the game over screen
+            .on_state_update(
+                APP_STATE_STAGE,
+                AppState::GameOver,
+                despawn_taken_pieces.system(),
+            );
     }
 }
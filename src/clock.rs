@@ -0,0 +1,120 @@
+use std::time::Duration;
+
+use crate::position::PieceColor;
+
+/// Time given back after each move
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TimeBonus {
+    None,
+    /// Fischer increment, added after every move
+    Increment(Duration),
+    /// Bronstein delay, giving back the time used on the move up to this much
+    Delay(Duration),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TimeControl {
+    pub base: Duration,
+    pub bonus: TimeBonus,
+}
+
+/// A chess clock with one timer per side, where only the side to move's timer runs
+#[derive(Clone, Debug)]
+pub struct Clock {
+    control: TimeControl,
+    remaining: [Duration; 2],
+    running: PieceColor,
+    /// Time used on the current move so far
+    used: Duration,
+    /// Time left on both sides as each move was made, before its bonus
+    history: Vec<[Duration; 2]>,
+}
+
+impl Clock {
+    /// Starts both sides on the base time, with `running` to move
+    pub fn new(control: TimeControl, running: PieceColor) -> Self {
+        Self {
+            control,
+            remaining: [control.base; 2],
+            running,
+            used: Duration::default(),
+            history: Vec::new(),
+        }
+    }
+
+    pub fn time_control(&self) -> TimeControl {
+        self.control
+    }
+
+    pub fn remaining(&self, color: PieceColor) -> Duration {
+        self.remaining[color.index()]
+    }
+
+    /// Side whose timer is running
+    pub fn running(&self) -> PieceColor {
+        self.running
+    }
+
+    /// Counts `elapsed` against the side to move
+    pub fn tick(&mut self, elapsed: Duration) {
+        let remaining = &mut self.remaining[self.running.index()];
+        *remaining = remaining.saturating_sub(elapsed);
+        self.used += elapsed;
+    }
+
+    /// Ends the running side's move, adding its bonus and starting the other side's timer
+    pub fn press(&mut self) {
+        if self.flagged().is_some() {
+            return;
+        }
+
+        let bonus = match self.control.bonus {
+            TimeBonus::None => Duration::default(),
+            TimeBonus::Increment(increment) => increment,
+            TimeBonus::Delay(delay) => delay.min(self.used),
+        };
+        self.history.push(self.remaining);
+        self.remaining[self.running.index()] += bonus;
+        self.switch_to(self.running.opposite());
+    }
+
+    /// Starts `color`'s timer without giving anyone a bonus
+    pub fn switch_to(&mut self, color: PieceColor) {
+        self.running = color;
+        self.used = Duration::default();
+    }
+
+    /// Takes back the last move. Both sides get the time they had as it was made, without its
+    /// bonus, and the timer of the side that made it runs again.
+    pub fn take_back(&mut self) {
+        if let Some(remaining) = self.history.pop() {
+            self.remaining = remaining;
+        }
+        self.switch_to(self.running.opposite());
+    }
+
+    /// Side that has run out of time, if any
+    pub fn flagged(&self) -> Option<PieceColor> {
+        [PieceColor::White, PieceColor::Black]
+            .iter()
+            .copied()
+            .find(|&color| self.remaining(color) == Duration::default())
+    }
+}
+
+/// Formats time left like `4:05`, with tenths of a second under ten seconds, like `0:09.3`
+pub fn format_clock(time: Duration) -> String {
+    let seconds = time.as_secs();
+    if seconds < 10 {
+        format!("0:{:02}.{}", seconds, time.subsec_millis() / 100)
+    } else if seconds < 3600 {
+        format!("{}:{:02}", seconds / 60, seconds % 60)
+    } else {
+        format!(
+            "{}:{:02}:{:02}",
+            seconds / 3600,
+            seconds / 60 % 60,
+            seconds % 60
+        )
+    }
+}
@@ -0,0 +1,240 @@
+use std::{error::Error, fmt};
+
+use crate::position::*;
+
+/// FEN of the standard starting position
+pub const START_FEN: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum FenError {
+    MissingField(&'static str),
+    InvalidBoard(String),
+    InvalidSideToMove(String),
+    InvalidCastling(String),
+    InvalidEnPassant(String),
+    InvalidCounter(String),
+}
+
+impl fmt::Display for FenError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::MissingField(field) => write!(f, "FEN is missing the {} field", field),
+            Self::InvalidBoard(board) => write!(f, "invalid FEN piece placement '{}'", board),
+            Self::InvalidSideToMove(side) => write!(f, "invalid FEN side to move '{}'", side),
+            Self::InvalidCastling(castling) => {
+                write!(f, "invalid FEN castling rights '{}'", castling)
+            }
+            Self::InvalidEnPassant(square) => {
+                write!(f, "invalid FEN en passant square '{}'", square)
+            }
+            Self::InvalidCounter(counter) => write!(f, "invalid FEN move counter '{}'", counter),
+        }
+    }
+}
+
+impl Error for FenError {}
+
+fn piece_from_char(c: char) -> Option<(PieceColor, PieceType)> {
+    let color = if c.is_ascii_uppercase() {
+        PieceColor::White
+    } else {
+        PieceColor::Black
+    };
+    let piece_type = match c.to_ascii_lowercase() {
+        'k' => PieceType::King,
+        'q' => PieceType::Queen,
+        'b' => PieceType::Bishop,
+        'n' => PieceType::Knight,
+        'r' => PieceType::Rook,
+        'p' => PieceType::Pawn,
+        _ => return None,
+    };
+    Some((color, piece_type))
+}
+
+/// Returns the FEN letter of a piece, uppercase for White
+pub fn piece_to_char(color: PieceColor, piece_type: PieceType) -> char {
+    let c = match piece_type {
+        PieceType::King => 'k',
+        PieceType::Queen => 'q',
+        PieceType::Bishop => 'b',
+        PieceType::Knight => 'n',
+        PieceType::Rook => 'r',
+        PieceType::Pawn => 'p',
+    };
+    match color {
+        PieceColor::White => c.to_ascii_uppercase(),
+        PieceColor::Black => c,
+    }
+}
+
+impl Position {
+    /// Parses a position from Forsyth-Edwards Notation.
+    ///
+    /// The move counters may be left out, in which case they default to `0 1`.
+    pub fn from_fen(fen: &str) -> Result<Self, FenError> {
+        let mut fields = fen.split_whitespace();
+        let mut position = Self::empty();
+
+        // Piece placement, from row 7 down to row 0
+        let board = fields
+            .next()
+            .ok_or(FenError::MissingField("piece placement"))?;
+        let rows: Vec<&str> = board.split('/').collect();
+        if rows.len() != 8 {
+            return Err(FenError::InvalidBoard(board.to_string()));
+        }
+        for (i, row) in rows.iter().enumerate() {
+            let x = 7 - i as u8;
+            let mut y = 0u8;
+            for c in row.chars() {
+                if let Some(empty) = c.to_digit(10) {
+                    // A row can't have more than 8 squares
+                    y = match y.checked_add(empty as u8) {
+                        Some(next) if empty > 0 && next <= 8 => next,
+                        _ => return Err(FenError::InvalidBoard(board.to_string())),
+                    };
+                } else if let Some(piece) = piece_from_char(c) {
+                    if y >= 8 {
+                        return Err(FenError::InvalidBoard(board.to_string()));
+                    }
+                    position.set_piece((x, y), Some(piece));
+                    y += 1;
+                } else {
+                    return Err(FenError::InvalidBoard(board.to_string()));
+                }
+            }
+            if y != 8 {
+                return Err(FenError::InvalidBoard(board.to_string()));
+            }
+        }
+        // Each side needs exactly one king
+        for &color in &[PieceColor::White, PieceColor::Black] {
+            let kings = position
+                .pieces()
+                .filter(|&(_, piece_color, piece_type)| {
+                    piece_color == color && piece_type == PieceType::King
+                })
+                .count();
+            if kings != 1 {
+                return Err(FenError::InvalidBoard(board.to_string()));
+            }
+        }
+
+        let side = fields
+            .next()
+            .ok_or(FenError::MissingField("side to move"))?;
+        position.set_side_to_move(match side {
+            "w" => PieceColor::White,
+            "b" => PieceColor::Black,
+            _ => return Err(FenError::InvalidSideToMove(side.to_string())),
+        });
+
+        let castling = fields
+            .next()
+            .ok_or(FenError::MissingField("castling rights"))?;
+        let mut rights = CastlingRights::default();
+        if castling != "-" {
+            for c in castling.chars() {
+                match c {
+                    'K' => rights.white_king_side = true,
+                    'Q' => rights.white_queen_side = true,
+                    'k' => rights.black_king_side = true,
+                    'q' => rights.black_queen_side = true,
+                    _ => return Err(FenError::InvalidCastling(castling.to_string())),
+                }
+            }
+        }
+        position.set_castling_rights(rights);
+
+        let en_passant = fields
+            .next()
+            .ok_or(FenError::MissingField("en passant square"))?;
+        if en_passant != "-" {
+            // The square is behind a pawn of the side that just moved
+            let row = match position.side_to_move() {
+                PieceColor::White => 5,
+                PieceColor::Black => 2,
+            };
+            match parse_square(en_passant) {
+                Some(square) if square.0 == row => position.set_en_passant(Some(square)),
+                _ => return Err(FenError::InvalidEnPassant(en_passant.to_string())),
+            }
+        }
+
+        if let Some(halfmove_clock) = fields.next() {
+            position.set_halfmove_clock(
+                halfmove_clock
+                    .parse()
+                    .map_err(|_| FenError::InvalidCounter(halfmove_clock.to_string()))?,
+            );
+        }
+        if let Some(fullmove_number) = fields.next() {
+            position.set_fullmove_number(
+                fullmove_number
+                    .parse()
+                    .map_err(|_| FenError::InvalidCounter(fullmove_number.to_string()))?,
+            );
+        }
+
+        Ok(position)
+    }
+
+    /// Writes the position in Forsyth-Edwards Notation
+    pub fn to_fen(&self) -> String {
+        let mut fen = String::new();
+
+        for x in (0..8).rev() {
+            let mut empty = 0;
+            for y in 0..8 {
+                match self.piece_at((x, y)) {
+                    Some((color, piece_type)) => {
+                        if empty > 0 {
+                            fen.push_str(&empty.to_string());
+                            empty = 0;
+                        }
+                        fen.push(piece_to_char(color, piece_type));
+                    }
+                    None => empty += 1,
+                }
+            }
+            if empty > 0 {
+                fen.push_str(&empty.to_string());
+            }
+            if x > 0 {
+                fen.push('/');
+            }
+        }
+
+        fen.push_str(match self.side_to_move() {
+            PieceColor::White => " w ",
+            PieceColor::Black => " b ",
+        });
+
+        let rights = self.castling_rights();
+        let castling: String = [
+            (rights.white_king_side, 'K'),
+            (rights.white_queen_side, 'Q'),
+            (rights.black_king_side, 'k'),
+            (rights.black_queen_side, 'q'),
+        ]
+        .iter()
+        .filter(|(allowed, _)| *allowed)
+        .map(|(_, c)| *c)
+        .collect();
+        fen.push_str(if castling.is_empty() { "-" } else { &castling });
+
+        fen.push(' ');
+        match self.en_passant() {
+            Some(square) => fen.push_str(&square_name(square)),
+            None => fen.push('-'),
+        }
+
+        fen.push_str(&format!(
+            " {} {}",
+            self.halfmove_clock(),
+            self.fullmove_number()
+        ));
+        fen
+    }
+}
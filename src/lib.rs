@@ -0,0 +1,13 @@
+//! Chess rules that don't depend on Bevy, shared by the game and any other frontend.
+
+pub mod bitboard;
+pub mod clock;
+pub mod fen;
+pub mod perft;
+pub mod pgn;
+pub mod position;
+pub mod san;
+pub mod search;
+pub mod transposition;
+pub mod uci;
+pub mod zobrist;
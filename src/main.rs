@@ -1,5 +1,11 @@
 use bevy::prelude::*;
+use bevy_chess::{
+    clock::{Clock, TimeBonus, TimeControl},
+    pgn::{parse_pgn, GameRecord},
+    position::Position,
+};
 use bevy_mod_picking::*;
+use std::{env, fmt, fs, process, time::Duration};
 
 mod pieces;
 use pieces::*;
@@ -7,9 +13,15 @@ mod board;
 use board::*;
 mod ui;
 use ui::*;
+mod ai;
+use ai::*;
+mod menu;
+use menu::*;
 
 #[bevy_main]
 fn main() {
+    let options = parse_args();
+
     App::build()
         // Set antialiasing to use 4 samples
         .add_resource(Msaa { samples: 4 })
@@ -20,26 +32,187 @@ fn main() {
             height: 600.,
             ..Default::default()
         })
+        .add_resource(options.record.start.clone())
+        .add_resource(options.record)
+        .add_resource(options.upcoming_moves)
+        .add_resource(options.players)
+        .add_resource(options.engine_settings)
+        .add_resource(options.game_clock)
+        .add_resource(options.new_game_options)
+        .add_resource(options.drag_options)
         .add_plugins(DefaultPlugins)
+        // The plugins below add systems for each screen to this stage
+        .add_resource(State::new(options.state))
+        .add_stage_after(
+            stage::UPDATE,
+            APP_STATE_STAGE,
+            StateStage::<AppState>::default(),
+        )
         .add_plugin(PickingPlugin)
         .add_plugin(BoardPlugin)
         .add_plugin(PiecesPlugin)
         .add_plugin(UiPlugin)
+        .add_plugin(AiPlugin)
+        .add_plugin(MenuPlugin)
         .add_startup_system(setup.system())
         .run();
 }
 
+/// Everything set up from the command line
+struct Options {
+    record: GameRecord,
+    upcoming_moves: UpcomingMoves,
+    players: Players,
+    engine_settings: EngineSettings,
+    game_clock: GameClock,
+    new_game_options: NewGameOptions,
+    drag_options: DragOptions,
+    /// Screen to start on
+    state: AppState,
+}
+
+/// Sets up the game from the command line:
+///
+/// - `--fen <FEN>` and `--fen-file <path>` start from a custom position.
+/// - `--pgn <path> [game number]` loads a saved game to step through.
+/// - `--white <human|engine|uci>` and `--black <human|engine|uci>` pick who plays each side.
+/// - `--uci-engine <command>` runs an external UCI engine for the `uci` players.
+/// - `--depth <plies>` and `--think-time <milliseconds>` limit the engines' search.
+/// - `--time <minutes>` plays with clocks, plus `--increment <seconds>` or `--delay <seconds>`.
+/// - `--drag` lets pieces be dragged to their new square as well as clicked there.
+///
+/// Giving a position or a game skips the main menu.
+fn parse_args() -> Options {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let read = |path: Option<&String>| {
+        let path = path.unwrap_or_else(|| exit_with_error("Missing file path"));
+        fs::read_to_string(path)
+            .unwrap_or_else(|err| exit_with_error(format!("Couldn't read {}: {}", path, err)))
+    };
+    let number = |value: Option<&String>, name: &str| -> u64 {
+        value
+            .and_then(|value| value.parse().ok())
+            .unwrap_or_else(|| exit_with_error(format!("Invalid {}", name)))
+    };
+    let player = |value: Option<&String>| match value.map(String::as_str) {
+        Some("human") => PlayerKind::Human,
+        Some("engine") => PlayerKind::Engine,
+        Some("uci") => PlayerKind::Uci,
+        _ => exit_with_error("Players must be 'human', 'engine' or 'uci'"),
+    };
+
+    let mut options = Options {
+        record: GameRecord::new(Position::default()),
+        upcoming_moves: UpcomingMoves::default(),
+        players: Players::default(),
+        engine_settings: EngineSettings::default(),
+        game_clock: GameClock::default(),
+        new_game_options: NewGameOptions::default(),
+        drag_options: DragOptions::default(),
+        state: AppState::Menu,
+    };
+    let mut new_game = true;
+    let mut time = None;
+    let mut bonus = TimeBonus::None;
+
+    let mut i = 0;
+    while i < args.len() {
+        let value = args.get(i + 1);
+        match args[i].as_str() {
+            "--pgn" => {
+                let mut games = parse_pgn(&read(value)).unwrap_or_else(|err| exit_with_error(err));
+
+                // The game number is optional
+                let game_number = args.get(i + 2).filter(|arg| !arg.starts_with("--"));
+                let index = game_number.map_or(1, |n| number(Some(n), "game number")) as usize;
+                if index == 0 || index > games.len() {
+                    exit_with_error(format!("The file only has {} game(s)", games.len()));
+                }
+                if game_number.is_some() {
+                    i += 1;
+                }
+
+                // Start from the beginning, with every move ready to step through
+                options.record = games.swap_remove(index - 1);
+                let mut moves = std::mem::take(&mut options.record.moves);
+                moves.reverse();
+                options.upcoming_moves = UpcomingMoves { moves };
+                options.state = AppState::Playing;
+                new_game = false;
+            }
+            "--fen" | "--fen-file" => {
+                let fen = match args[i].as_str() {
+                    "--fen" => value.cloned().unwrap_or_default(),
+                    _ => read(value),
+                };
+                options.record.start =
+                    Position::from_fen(&fen).unwrap_or_else(|err| exit_with_error(err));
+                // New games from the main menu start there too
+                options.new_game_options.fen = Some(fen.trim().to_string());
+                options.state = AppState::Playing;
+            }
+            "--white" => options.players.white = player(value),
+            "--black" => options.players.black = player(value),
+            "--uci-engine" => {
+                let command = value.unwrap_or_else(|| exit_with_error("Missing engine command"));
+                options.engine_settings.uci_command = Some(command.clone());
+            }
+            "--depth" => options.engine_settings.depth = Some(number(value, "depth") as u32),
+            "--think-time" => {
+                options.engine_settings.think_time =
+                    Duration::from_millis(number(value, "think time"))
+            }
+            "--time" => time = Some(Duration::from_secs(number(value, "time") * 60)),
+            "--increment" => {
+                bonus = TimeBonus::Increment(Duration::from_secs(number(value, "increment")))
+            }
+            "--delay" => bonus = TimeBonus::Delay(Duration::from_secs(number(value, "delay"))),
+            "--drag" => {
+                // This one takes no value
+                options.drag_options.enabled = true;
+                i += 1;
+                continue;
+            }
+            arg => exit_with_error(format!("Unknown argument '{}'", arg)),
+        }
+        i += 2;
+    }
+
+    if let Some(base) = time {
+        let control = TimeControl { base, bonus };
+        let running = options.record.start.side_to_move();
+        options.game_clock.clock = Some(Clock::new(control, running));
+
+        // PGN only has a way to write increments
+        let tag = match bonus {
+            TimeBonus::Increment(increment) => {
+                format!("{}+{}", base.as_secs(), increment.as_secs())
+            }
+            _ => base.as_secs().to_string(),
+        };
+        options.record.set_tag("TimeControl", &tag);
+    }
+
+    if new_game {
+        set_new_game_tags(&mut options.record);
+    }
+    options
+}
+
+fn exit_with_error(message: impl fmt::Display) -> ! {
+    eprintln!("{}", message);
+    process::exit(1);
+}
+
 fn setup(commands: &mut Commands) {
     commands
         // Camera
         .spawn(Camera3dBundle {
-            transform: Transform::from_matrix(Mat4::from_rotation_translation(
-                Quat::from_xyzw(-0.3, -0.5, -0.3, 0.5).normalize(),
-                Vec3::new(-7.0, 20.0, 4.0),
-            )),
+            transform: camera_transform(&BoardView::default()),
             ..Default::default()
         })
         .with(PickSource::default())
+        .with(BoardCamera)
         // Light
         .spawn(LightBundle {
             transform: Transform::from_translation(Vec3::new(4.0, 8.0, 4.0)),
@@ -0,0 +1,405 @@
+use crate::{board::*, ui::spawn_button};
+use bevy::{app::AppExit, prelude::*};
+use bevy_chess::{
+    pgn::{parse_pgn, GameRecord},
+    position::{GameOver, PieceColor, Position},
+};
+use std::fs;
+
+/// Screens the game can be on
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum AppState {
+    Menu,
+    Settings,
+    Playing,
+    Paused,
+    GameOver,
+}
+
+/// Stage running the systems of the current `AppState`.
+/// Add it before the plugins, since they add their systems to it.
+pub const APP_STATE_STAGE: &str = "app_state";
+
+/// Switches screens when the state stage next runs. If another switch is already queued, that one wins.
+fn go_to(state: &mut State<AppState>, next: AppState) {
+    let _ = state.set_next(next);
+}
+
+// Component to mark the root node of a menu screen
+struct MenuScreen;
+
+// Component for the buttons on the menu screens
+#[derive(Clone, Copy)]
+enum MenuButton {
+    NewGame,
+    Load,
+    LoadPosition,
+    PastePosition,
+    Settings,
+    Quit,
+    Resume,
+    Rematch,
+    Review,
+    MainMenu,
+    Back,
+    TogglePlayer(PieceColor),
+    ToggleAutoQueen,
+    ToggleDrag,
+}
+
+/// Spawn a screen with a title and a column of buttons, over the top of the board
+fn spawn_menu(
+    commands: &mut Commands,
+    asset_server: &AssetServer,
+    color_materials: &mut Assets<ColorMaterial>,
+    title: String,
+    buttons: Vec<(String, MenuButton)>,
+) {
+    let font = asset_server.load("fonts/FiraSans-Bold.ttf");
+    let material = color_materials.add(Color::rgba(0., 0., 0., 0.7).into());
+    let button_material = color_materials.add(Color::rgb(0.15, 0.15, 0.15).into());
+
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                size: Size::new(Val::Percent(100.), Val::Percent(100.)),
+                flex_direction: FlexDirection::ColumnReverse,
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..Default::default()
+            },
+            material,
+            ..Default::default()
+        })
+        .with(MenuScreen)
+        .with_children(|parent| {
+            parent.spawn(TextBundle {
+                style: Style {
+                    margin: Rect::all(Val::Px(16.)),
+                    ..Default::default()
+                },
+                text: Text {
+                    value: title,
+                    font: font.clone(),
+                    style: TextStyle {
+                        font_size: 50.0,
+                        color: Color::rgb(0.8, 0.8, 0.8),
+                        ..Default::default()
+                    },
+                },
+                ..Default::default()
+            });
+
+            for (label, button) in buttons {
+                spawn_button(parent, label, font.clone(), button_material.clone(), button);
+            }
+        });
+}
+
+fn despawn_menu(commands: &mut Commands, query: Query<Entity, With<MenuScreen>>) {
+    for entity in query.iter() {
+        commands.despawn_recursive(entity);
+    }
+}
+
+fn spawn_main_menu(
+    commands: &mut Commands,
+    asset_server: Res<AssetServer>,
+    mut color_materials: ResMut<Assets<ColorMaterial>>,
+) {
+    spawn_menu(
+        commands,
+        &asset_server,
+        &mut color_materials,
+        "Chess!".to_string(),
+        vec![
+            ("New game".to_string(), MenuButton::NewGame),
+            ("Load game".to_string(), MenuButton::Load),
+            ("Load position".to_string(), MenuButton::LoadPosition),
+            ("Paste position".to_string(), MenuButton::PastePosition),
+            ("Settings".to_string(), MenuButton::Settings),
+            ("Quit".to_string(), MenuButton::Quit),
+        ],
+    );
+}
+
+fn spawn_settings_menu(
+    commands: &mut Commands,
+    asset_server: Res<AssetServer>,
+    mut color_materials: ResMut<Assets<ColorMaterial>>,
+    players: Res<Players>,
+    promotion_options: Res<PromotionOptions>,
+    drag_options: Res<DragOptions>,
+) {
+    let buttons = vec![
+        MenuButton::TogglePlayer(PieceColor::White),
+        MenuButton::TogglePlayer(PieceColor::Black),
+        MenuButton::ToggleAutoQueen,
+        MenuButton::ToggleDrag,
+    ];
+    let mut buttons: Vec<_> = buttons
+        .into_iter()
+        .filter_map(|button| {
+            setting_label(button, &players, &promotion_options, &drag_options)
+                .map(|label| (label, button))
+        })
+        .collect();
+    buttons.push(("Back".to_string(), MenuButton::Back));
+
+    spawn_menu(
+        commands,
+        &asset_server,
+        &mut color_materials,
+        "Settings".to_string(),
+        buttons,
+    );
+}
+
+/// Label of a settings button, showing the current value
+fn setting_label(
+    button: MenuButton,
+    players: &Players,
+    promotion_options: &PromotionOptions,
+    drag_options: &DragOptions,
+) -> Option<String> {
+    match button {
+        MenuButton::TogglePlayer(color) => {
+            let player = match players.get(color) {
+                PlayerKind::Human => "human",
+                PlayerKind::Engine => "engine",
+                PlayerKind::Uci => "UCI engine",
+            };
+            Some(format!("{}: {}", color, player))
+        }
+        MenuButton::ToggleAutoQueen => Some(format!(
+            "Auto-queen: {}",
+            on_off(promotion_options.auto_queen)
+        )),
+        MenuButton::ToggleDrag => Some(format!("Drag pieces: {}", on_off(drag_options.enabled))),
+        _ => None,
+    }
+}
+
+fn on_off(enabled: bool) -> &'static str {
+    if enabled {
+        "on"
+    } else {
+        "off"
+    }
+}
+
+/// Keep the settings buttons' labels in step with the settings
+fn settings_labels(
+    players: Res<Players>,
+    promotion_options: Res<PromotionOptions>,
+    drag_options: Res<DragOptions>,
+    buttons_query: Query<(&MenuButton, &Children)>,
+    mut text_query: Query<&mut Text>,
+) {
+    for (button, children) in buttons_query.iter() {
+        let label = match setting_label(*button, &players, &promotion_options, &drag_options) {
+            Some(label) => label,
+            None => continue,
+        };
+
+        for child in children.iter() {
+            if let Ok(mut text) = text_query.get_mut(*child) {
+                if text.value != label {
+                    text.value = label.clone();
+                }
+            }
+        }
+    }
+}
+
+fn spawn_pause_menu(
+    commands: &mut Commands,
+    asset_server: Res<AssetServer>,
+    mut color_materials: ResMut<Assets<ColorMaterial>>,
+) {
+    spawn_menu(
+        commands,
+        &asset_server,
+        &mut color_materials,
+        "Paused".to_string(),
+        vec![
+            ("Resume".to_string(), MenuButton::Resume),
+            ("Main menu".to_string(), MenuButton::MainMenu),
+        ],
+    );
+}
+
+fn spawn_game_over_screen(
+    commands: &mut Commands,
+    asset_server: Res<AssetServer>,
+    mut color_materials: ResMut<Assets<ColorMaterial>>,
+    status: Res<GameStatus>,
+) {
+    let title = match status.game_over {
+        Some(game_over) => game_over.to_string(),
+        None => "Game over".to_string(),
+    };
+
+    spawn_menu(
+        commands,
+        &asset_server,
+        &mut color_materials,
+        title,
+        vec![
+            ("Rematch".to_string(), MenuButton::Rematch),
+            ("Review game".to_string(), MenuButton::Review),
+            ("Main menu".to_string(), MenuButton::MainMenu),
+        ],
+    );
+}
+
+fn menu_buttons(
+    mut state: ResMut<State<AppState>>,
+    record: Res<GameRecord>,
+    new_game_options: Res<NewGameOptions>,
+    mut players: ResMut<Players>,
+    mut promotion_options: ResMut<PromotionOptions>,
+    mut drag_options: ResMut<DragOptions>,
+    mut new_game_events: ResMut<Events<NewGameEvent>>,
+    mut app_exit_events: ResMut<Events<AppExit>>,
+    query: Query<(&Interaction, &MenuButton), Mutated<Interaction>>,
+) {
+    for (interaction, button) in query.iter() {
+        if *interaction != Interaction::Clicked {
+            continue;
+        }
+
+        match *button {
+            MenuButton::NewGame => {
+                let event = match &new_game_options.fen {
+                    Some(fen) => NewGameEvent::from_fen(fen, &record),
+                    None => Ok(NewGameEvent::from_start(Position::default(), &record)),
+                };
+                match event {
+                    Ok(event) => {
+                        new_game_events.send(event);
+                        go_to(&mut state, AppState::Playing);
+                    }
+                    Err(err) => eprintln!("Couldn't start a new game: {}", err),
+                }
+            }
+            MenuButton::Load => match load_saved_game() {
+                Ok(record) => {
+                    new_game_events.send(NewGameEvent {
+                        record,
+                        swap_colors: false,
+                    });
+                    go_to(&mut state, AppState::Playing);
+                }
+                Err(err) => eprintln!("{}", err),
+            },
+            MenuButton::LoadPosition | MenuButton::PastePosition => {
+                let fen = match *button {
+                    MenuButton::PastePosition => paste_fen(),
+                    _ => fs::read_to_string(FEN_FILE)
+                        .map_err(|err| format!("Couldn't read {}: {}", FEN_FILE, err)),
+                };
+                let event = fen.and_then(|fen| {
+                    NewGameEvent::from_fen(&fen, &record)
+                        .map_err(|err| format!("Couldn't load the position: {}", err))
+                });
+                match event {
+                    Ok(event) => {
+                        new_game_events.send(event);
+                        go_to(&mut state, AppState::Playing);
+                    }
+                    Err(err) => eprintln!("{}", err),
+                }
+            }
+            MenuButton::Rematch => {
+                new_game_events.send(NewGameEvent::rematch(&record));
+                go_to(&mut state, AppState::Playing);
+            }
+            MenuButton::Settings => go_to(&mut state, AppState::Settings),
+            MenuButton::Quit => app_exit_events.send(AppExit),
+            MenuButton::Resume | MenuButton::Review => go_to(&mut state, AppState::Playing),
+            MenuButton::MainMenu | MenuButton::Back => go_to(&mut state, AppState::Menu),
+            MenuButton::TogglePlayer(color) => {
+                // The external engine only starts with the game, so it can't be picked here
+                let player = match players.get(color) {
+                    PlayerKind::Human => PlayerKind::Engine,
+                    PlayerKind::Engine | PlayerKind::Uci => PlayerKind::Human,
+                };
+                match color {
+                    PieceColor::White => players.white = player,
+                    PieceColor::Black => players.black = player,
+                }
+            }
+            MenuButton::ToggleAutoQueen => {
+                promotion_options.auto_queen = !promotion_options.auto_queen
+            }
+            MenuButton::ToggleDrag => drag_options.enabled = !drag_options.enabled,
+        }
+    }
+}
+
+/// Reads the first game in the save file
+fn load_saved_game() -> Result<GameRecord, String> {
+    let text = fs::read_to_string(SAVE_FILE)
+        .map_err(|err| format!("Couldn't read {}: {}", SAVE_FILE, err))?;
+    let games = parse_pgn(&text).map_err(|err| format!("Couldn't load {}: {}", SAVE_FILE, err))?;
+    games
+        .into_iter()
+        .next()
+        .ok_or_else(|| format!("There's no game in {}", SAVE_FILE))
+}
+
+/// Pause and resume with Escape
+fn toggle_pause(keyboard_input: Res<Input<KeyCode>>, mut state: ResMut<State<AppState>>) {
+    if !keyboard_input.just_pressed(KeyCode::Escape) {
+        return;
+    }
+
+    match state.current() {
+        AppState::Playing => go_to(&mut state, AppState::Paused),
+        AppState::Paused => go_to(&mut state, AppState::Playing),
+        _ => {}
+    }
+}
+
+/// Show the game over screen once the game finishes
+fn show_game_over(
+    mut event_reader: Local<EventReader<GameOver>>,
+    events: Res<Events<GameOver>>,
+    mut state: ResMut<State<AppState>>,
+) {
+    if event_reader.iter(&events).next().is_some() {
+        go_to(&mut state, AppState::GameOver);
+    }
+}
+
+pub struct MenuPlugin;
+impl Plugin for MenuPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_system(menu_buttons.system())
+            .add_system(toggle_pause.system())
+            .on_state_enter(APP_STATE_STAGE, AppState::Menu, spawn_main_menu.system())
+            .on_state_exit(APP_STATE_STAGE, AppState::Menu, despawn_menu.system())
+            .on_state_enter(
+                APP_STATE_STAGE,
+                AppState::Settings,
+                spawn_settings_menu.system(),
+            )
+            .on_state_update(
+                APP_STATE_STAGE,
+                AppState::Settings,
+                settings_labels.system(),
+            )
+            .on_state_exit(APP_STATE_STAGE, AppState::Settings, despawn_menu.system())
+            .on_state_update(APP_STATE_STAGE, AppState::Playing, show_game_over.system())
+            .on_state_enter(APP_STATE_STAGE, AppState::Paused, spawn_pause_menu.system())
+            .on_state_exit(APP_STATE_STAGE, AppState::Paused, despawn_menu.system())
+            .on_state_enter(
+                APP_STATE_STAGE,
+                AppState::GameOver,
+                spawn_game_over_screen.system(),
+            )
+            .on_state_exit(APP_STATE_STAGE, AppState::GameOver, despawn_menu.system());
+    }
+}
@@ -0,0 +1,46 @@
+use crate::position::*;
+
+impl Position {
+    /// Counts the leaf nodes of the legal move tree `depth` plies deep, to check the move
+    /// generator against known results
+    pub fn perft(&self, depth: u32) -> u64 {
+        perft(&mut self.clone(), depth)
+    }
+
+    /// Splits the perft count by first move, to narrow down which move is counted wrong
+    pub fn divide(&self, depth: u32) -> Vec<(Move, u64)> {
+        let mut position = self.clone();
+        position
+            .legal_moves()
+            .into_iter()
+            .map(|mv| {
+                position.make_move(mv);
+                let nodes = perft(&mut position, depth.saturating_sub(1));
+                position.unmake_move();
+                (mv, nodes)
+            })
+            .collect()
+    }
+}
+
+fn perft(position: &mut Position, depth: u32) -> u64 {
+    if depth == 0 {
+        return 1;
+    }
+
+    let moves = position.legal_moves();
+    // Leaves don't need to be played out
+    if depth == 1 {
+        return moves.len() as u64;
+    }
+
+    moves
+        .into_iter()
+        .map(|mv| {
+            position.make_move(mv);
+            let nodes = perft(position, depth - 1);
+            position.unmake_move();
+            nodes
+        })
+        .sum()
+}
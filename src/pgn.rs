@@ -0,0 +1,390 @@
+use std::{error::Error, fmt};
+
+use crate::{fen::FenError, position::*};
+
+/// Tags every PGN game must have, in the order they're written
+pub const SEVEN_TAG_ROSTER: [&str; 7] =
+    ["Event", "Site", "Date", "Round", "White", "Black", "Result"];
+
+/// A game as the moves played from a starting position, plus its PGN tags
+#[derive(Clone)]
+pub struct GameRecord {
+    pub tags: Vec<(String, String)>,
+    pub start: Position,
+    pub moves: Vec<Move>,
+}
+
+impl Default for GameRecord {
+    fn default() -> Self {
+        Self::new(Position::default())
+    }
+}
+
+impl GameRecord {
+    /// An empty record starting from `start`, with unknown values for the Seven Tag Roster
+    pub fn new(start: Position) -> Self {
+        let tags = SEVEN_TAG_ROSTER
+            .iter()
+            .map(|&name| {
+                let value = match name {
+                    "Date" => "????.??.??",
+                    "Result" => "*",
+                    _ => "?",
+                };
+                (name.to_string(), value.to_string())
+            })
+            .collect();
+
+        Self {
+            tags,
+            start,
+            moves: Vec::new(),
+        }
+    }
+
+    pub fn tag(&self, name: &str) -> Option<&str> {
+        self.tags
+            .iter()
+            .find(|(tag, _)| tag == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Sets a tag, replacing it if it's already there
+    pub fn set_tag(&mut self, name: &str, value: &str) {
+        match self.tags.iter_mut().find(|(tag, _)| tag == name) {
+            Some((_, old)) => *old = value.to_string(),
+            None => self.tags.push((name.to_string(), value.to_string())),
+        }
+    }
+
+    /// Sets the Result tag
+    pub fn set_result(&mut self, result: Option<GameResult>) {
+        self.set_tag("Result", result_tag(result));
+    }
+
+    /// The position after every recorded move has been played
+    pub fn position(&self) -> Position {
+        let mut position = self.start.clone();
+        for &mv in &self.moves {
+            position.make_move(mv);
+        }
+        position
+    }
+
+    /// The recorded moves in Standard Algebraic Notation
+    pub fn san_moves(&self) -> Vec<String> {
+        let mut position = self.start.clone();
+        self.moves
+            .iter()
+            .map(|&mv| {
+                let san = position.to_san(mv);
+                position.make_move(mv);
+                san
+            })
+            .collect()
+    }
+
+    /// Writes the game as PGN
+    pub fn to_pgn(&self) -> String {
+        let mut pgn = String::new();
+
+        let mut tags = self.tags.clone();
+        if self.start.to_fen() != Position::default().to_fen() {
+            tags.retain(|(name, _)| name != "SetUp" && name != "FEN");
+            tags.push(("SetUp".to_string(), "1".to_string()));
+            tags.push(("FEN".to_string(), self.start.to_fen()));
+        }
+        for (name, value) in &tags {
+            let value = value.replace('\\', "\\\\").replace('"', "\\\"");
+            pgn.push_str(&format!("[{} \"{}\"]\n", name, value));
+        }
+        pgn.push('\n');
+
+        // Movetext, wrapped to stay under 80 columns
+        let mut tokens = Vec::new();
+        let mut move_number = self.start.fullmove_number();
+        let mut color = self.start.side_to_move();
+        for (i, san) in self.san_moves().into_iter().enumerate() {
+            // Keep move numbers on the same line as their move
+            tokens.push(if color == PieceColor::White {
+                format!("{}. {}", move_number, san)
+            } else if i == 0 {
+                format!("{}... {}", move_number, san)
+            } else {
+                san
+            });
+
+            if color == PieceColor::Black {
+                move_number += 1;
+            }
+            color = color.opposite();
+        }
+        tokens.push(self.tag("Result").unwrap_or("*").to_string());
+
+        let mut line = String::new();
+        for token in tokens {
+            if !line.is_empty() && line.len() + 1 + token.len() > 79 {
+                pgn.push_str(&line);
+                pgn.push('\n');
+                line.clear();
+            }
+            if !line.is_empty() {
+                line.push(' ');
+            }
+            line.push_str(&token);
+        }
+        pgn.push_str(&line);
+        pgn.push('\n');
+
+        pgn
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PgnError {
+    InvalidTag(String),
+    InvalidFen(FenError),
+    IllegalMove {
+        game: usize,
+        ply: usize,
+        san: String,
+    },
+}
+
+impl fmt::Display for PgnError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::InvalidTag(tag) => write!(f, "invalid PGN tag '{}'", tag),
+            Self::InvalidFen(err) => write!(f, "invalid PGN FEN tag: {}", err),
+            Self::IllegalMove { game, ply, san } => write!(
+                f,
+                "illegal move '{}' at ply {} of game {}",
+                san,
+                ply + 1,
+                game + 1
+            ),
+        }
+    }
+}
+
+impl Error for PgnError {}
+
+enum Token {
+    Tag(String, String),
+    Move(String),
+    Result(String),
+}
+
+/// Splits PGN text into tags, moves and results, dropping comments, NAGs,
+/// move numbers and variations
+fn tokenize(text: &str) -> Result<Vec<Token>, PgnError> {
+    let mut tokens = Vec::new();
+    let mut chars = text.chars().peekable();
+    let mut variation_depth = 0u32;
+    let mut line_start = true;
+
+    while let Some(c) = chars.next() {
+        let was_line_start = line_start;
+        line_start = c == '\n';
+
+        match c {
+            // Escaped lines and rest-of-line comments
+            '%' if was_line_start => {
+                while chars.peek().is_some_and(|&c| c != '\n') {
+                    chars.next();
+                }
+            }
+            ';' => {
+                while chars.peek().is_some_and(|&c| c != '\n') {
+                    chars.next();
+                }
+            }
+            '{' => {
+                for c in &mut chars {
+                    if c == '}' {
+                        break;
+                    }
+                }
+            }
+            '(' => variation_depth += 1,
+            ')' => variation_depth = variation_depth.saturating_sub(1),
+            '[' if variation_depth == 0 => {
+                let mut tag = String::new();
+                let mut in_string = false;
+                while let Some(c) = chars.next() {
+                    match c {
+                        '\\' if in_string => {
+                            if let Some(escaped) = chars.next() {
+                                tag.push('\\');
+                                tag.push(escaped);
+                            }
+                            continue;
+                        }
+                        '"' => in_string = !in_string,
+                        ']' if !in_string => break,
+                        _ => {}
+                    }
+                    tag.push(c);
+                }
+                tokens.push(parse_tag(&tag)?);
+            }
+            _ if c.is_whitespace() => {}
+            _ => {
+                let mut symbol = c.to_string();
+                while let Some(&c) = chars.peek() {
+                    if c.is_whitespace() || "{}()[];".contains(c) {
+                        break;
+                    }
+                    symbol.push(c);
+                    chars.next();
+                }
+
+                if variation_depth > 0 || symbol.starts_with('$') {
+                    continue;
+                }
+                match symbol.as_str() {
+                    "1-0" | "0-1" | "1/2-1/2" | "*" => tokens.push(Token::Result(symbol)),
+                    _ => {
+                        // Move numbers like `12.` or `12...`, possibly glued to the move.
+                        // Digits without a dot are castling written with zeros, like `0-0`.
+                        let number = symbol.trim_start_matches(|c: char| c.is_ascii_digit());
+                        let san = if number.starts_with('.') {
+                            number
+                        } else {
+                            symbol.as_str()
+                        };
+                        let san = san.trim_start_matches('.');
+                        if !san.is_empty() {
+                            tokens.push(Token::Move(san.to_string()));
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(tokens)
+}
+
+fn parse_tag(tag: &str) -> Result<Token, PgnError> {
+    let tag = tag.trim();
+    let invalid = || PgnError::InvalidTag(tag.to_string());
+
+    let space = tag.find(char::is_whitespace).ok_or_else(invalid)?;
+    let value = tag[space..].trim();
+    if value.len() < 2 || !value.starts_with('"') || !value.ends_with('"') {
+        return Err(invalid());
+    }
+    let value = value[1..value.len() - 1]
+        .replace("\\\"", "\"")
+        .replace("\\\\", "\\");
+
+    Ok(Token::Tag(tag[..space].to_string(), value))
+}
+
+/// Reads every game in a PGN file. Comments, NAGs and variations are skipped.
+pub fn parse_pgn(text: &str) -> Result<Vec<GameRecord>, PgnError> {
+    let mut games = Vec::new();
+    let mut tags: Vec<(String, String)> = Vec::new();
+    let mut sans: Vec<String> = Vec::new();
+    let mut result = None;
+
+    let mut tokens = tokenize(text)?.into_iter();
+    loop {
+        let token = tokens.next();
+        let game_ended = match &token {
+            None => true,
+            Some(Token::Result(_)) => true,
+            // Tags after moves start the next game, even without a result
+            Some(Token::Tag(_, _)) => !sans.is_empty(),
+            Some(Token::Move(_)) => false,
+        };
+
+        if game_ended && (!tags.is_empty() || !sans.is_empty()) {
+            if let Some(Token::Result(value)) = &token {
+                result = Some(value.clone());
+            }
+            games.push(build_game(
+                games.len(),
+                std::mem::take(&mut tags),
+                std::mem::take(&mut sans),
+                result.take(),
+            )?);
+        }
+
+        match token {
+            None => break,
+            Some(Token::Tag(name, value)) => tags.push((name, value)),
+            Some(Token::Move(san)) => sans.push(san),
+            Some(Token::Result(_)) => {}
+        }
+    }
+
+    Ok(games)
+}
+
+fn build_game(
+    index: usize,
+    tags: Vec<(String, String)>,
+    sans: Vec<String>,
+    result: Option<String>,
+) -> Result<GameRecord, PgnError> {
+    let start = match tags.iter().find(|(name, _)| name == "FEN") {
+        Some((_, fen)) => Position::from_fen(fen).map_err(PgnError::InvalidFen)?,
+        None => Position::default(),
+    };
+
+    let mut record = GameRecord::new(start.clone());
+    for (name, value) in &tags {
+        if name != "SetUp" && name != "FEN" {
+            record.set_tag(name, value);
+        }
+    }
+    if let Some(result) = result {
+        record.set_tag("Result", &result);
+    }
+
+    let mut position = start;
+    for (ply, san) in sans.into_iter().enumerate() {
+        let mv = position.parse_san(&san).ok_or(PgnError::IllegalMove {
+            game: index,
+            ply,
+            san: san.clone(),
+        })?;
+        position.make_move(mv);
+        record.moves.push(mv);
+    }
+
+    Ok(record)
+}
+
+/// Returns the PGN result marker, `*` for a game still in progress
+pub fn result_tag(result: Option<GameResult>) -> &'static str {
+    match result {
+        Some(GameResult::Win(PieceColor::White)) => "1-0",
+        Some(GameResult::Win(PieceColor::Black)) => "0-1",
+        Some(GameResult::Draw) => "1/2-1/2",
+        None => "*",
+    }
+}
+
+/// Formats a Unix timestamp as a PGN `YYYY.MM.DD` date, in UTC
+pub fn pgn_date(unix_seconds: u64) -> String {
+    // Convert days since 1970-01-01 to a civil date, counting eras of 400 years from 0000-03-01
+    let days = (unix_seconds / 86_400) as i64 + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    format!("{:04}.{:02}.{:02}", year, month, day)
+}
@@ -1,42 +1,8 @@
-use std::fmt;
-
+use crate::{board::HistoryView, menu::*};
 use bevy::prelude::*;
+pub use bevy_chess::position::{PieceColor, PieceType, Position};
 
-#[derive(Clone, Copy, PartialEq)]
-pub enum PieceColor {
-    White,
-    Black,
-}
-
-impl PieceColor {
-    pub fn opposite(self) -> Self {
-        match self {
-            Self::White => Self::Black,
-            Self::Black => Self::White,
-        }
-    }
-}
-
-impl fmt::Display for PieceColor {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        match self {
-            Self::White => write!(f, "White"),
-            Self::Black => write!(f, "Black"),
-        }
-    }
-}
-
-#[derive(Clone, Copy, PartialEq)]
-pub enum PieceType {
-    King,
-    Queen,
-    Bishop,
-    Knight,
-    Rook,
-    Pawn,
-}
-
-struct PieceData {
+pub struct PieceData {
     black_color: Handle<StandardMaterial>,
     white_color: Handle<StandardMaterial>,
     pawn_mesh: Handle<Mesh>,
@@ -77,176 +43,11 @@ pub struct Piece {
     pub x: u8,
     pub y: u8,
 }
-impl Piece {
-    /// Returns the possible_positions that are available
-    pub fn is_move_valid(&self, new_position: (u8, u8), pieces: Vec<Piece>) -> bool {
-        // If there's a piece of the same color in the same square, it can't move
-        if color_of_square(new_position, &pieces) == Some(self.color) {
-            return false;
-        }
-
-        match self.piece_type {
-            PieceType::King => {
-                // Horizontal
-                ((self.x as i8 - new_position.0 as i8).abs() == 1
-                    && (self.y == new_position.1))
-                // Vertical
-                || ((self.y as i8 - new_position.1 as i8).abs() == 1
-                    && (self.x == new_position.0))
-                // Diagonal
-                || ((self.x as i8 - new_position.0 as i8).abs() == 1
-                    && (self.y as i8 - new_position.1 as i8).abs() == 1)
-            }
-            PieceType::Queen => {
-                is_path_empty((self.x, self.y), new_position, &pieces)
-                    && ((self.x as i8 - new_position.0 as i8).abs()
-                        == (self.y as i8 - new_position.1 as i8).abs()
-                        || ((self.x == new_position.0 && self.y != new_position.1)
-                            || (self.y == new_position.1 && self.x != new_position.0)))
-            }
-            PieceType::Bishop => {
-                is_path_empty((self.x, self.y), new_position, &pieces)
-                    && (self.x as i8 - new_position.0 as i8).abs()
-                        == (self.y as i8 - new_position.1 as i8).abs()
-            }
-            PieceType::Knight => {
-                ((self.x as i8 - new_position.0 as i8).abs() == 2
-                    && (self.y as i8 - new_position.1 as i8).abs() == 1)
-                    || ((self.x as i8 - new_position.0 as i8).abs() == 1
-                        && (self.y as i8 - new_position.1 as i8).abs() == 2)
-            }
-            PieceType::Rook => {
-                is_path_empty((self.x, self.y), new_position, &pieces)
-                    && ((self.x == new_position.0 && self.y != new_position.1)
-                        || (self.y == new_position.1 && self.x != new_position.0))
-            }
-            PieceType::Pawn => {
-                if self.color == PieceColor::White {
-                    // Normal move
-                    if new_position.0 as i8 - self.x as i8 == 1 && (self.y == new_position.1) {
-                        if color_of_square(new_position, &pieces).is_none() {
-                            return true;
-                        }
-                    }
-
-                    // Move 2 squares
-                    if self.x == 1
-                        && new_position.0 as i8 - self.x as i8 == 2
-                        && (self.y == new_position.1)
-                        && is_path_empty((self.x, self.y), new_position, &pieces)
-                    {
-                        if color_of_square(new_position, &pieces).is_none() {
-                            return true;
-                        }
-                    }
-
-                    // Take piece
-                    if new_position.0 as i8 - self.x as i8 == 1
-                        && (self.y as i8 - new_position.1 as i8).abs() == 1
-                    {
-                        if color_of_square(new_position, &pieces) == Some(PieceColor::Black) {
-                            return true;
-                        }
-                    }
-                } else {
-                    // Normal move
-                    if new_position.0 as i8 - self.x as i8 == -1 && (self.y == new_position.1) {
-                        if color_of_square(new_position, &pieces).is_none() {
-                            return true;
-                        }
-                    }
-
-                    // Move 2 squares
-                    if self.x == 6
-                        && new_position.0 as i8 - self.x as i8 == -2
-                        && (self.y == new_position.1)
-                        && is_path_empty((self.x, self.y), new_position, &pieces)
-                    {
-                        if color_of_square(new_position, &pieces).is_none() {
-                            return true;
-                        }
-                    }
-
-                    // Take piece
-                    if new_position.0 as i8 - self.x as i8 == -1
-                        && (self.y as i8 - new_position.1 as i8).abs() == 1
-                    {
-                        if color_of_square(new_position, &pieces) == Some(PieceColor::White) {
-                            return true;
-                        }
-                    }
-                }
-
-                false
-            }
-        }
-    }
-}
-
-fn is_path_empty(begin: (u8, u8), end: (u8, u8), pieces: &Vec<Piece>) -> bool {
-    // Same column
-    if begin.0 == end.0 {
-        for piece in pieces {
-            if piece.x == begin.0
-                && ((piece.y > begin.1 && piece.y < end.1)
-                    || (piece.y > end.1 && piece.y < begin.1))
-            {
-                return false;
-            }
-        }
-    }
-    // Same row
-    if begin.1 == end.1 {
-        for piece in pieces {
-            if piece.y == begin.1
-                && ((piece.x > begin.0 && piece.x < end.0)
-                    || (piece.x > end.0 && piece.x < begin.0))
-            {
-                return false;
-            }
-        }
-    }
-
-    // Diagonals
-    let x_diff = (begin.0 as i8 - end.0 as i8).abs();
-    let y_diff = (begin.1 as i8 - end.1 as i8).abs();
-    if x_diff == y_diff {
-        for i in 1..x_diff {
-            let pos = if begin.0 < end.0 && begin.1 < end.1 {
-                // left bottom - right top
-                (begin.0 + i as u8, begin.1 + i as u8)
-            } else if begin.0 < end.0 && begin.1 > end.1 {
-                // left top - right bottom
-                (begin.0 + i as u8, begin.1 - i as u8)
-            } else if begin.0 > end.0 && begin.1 < end.1 {
-                // right bottom - left top
-                (begin.0 - i as u8, begin.1 + i as u8)
-            } else {
-                // begin.0 > end.0 && begin.1 > end.1
-                // right top - left bottom
-                (begin.0 - i as u8, begin.1 - i as u8)
-            };
-
-            if color_of_square(pos, pieces).is_some() {
-                return false;
-            }
-        }
-    }
-
-    true
-}
 
-/// Returns None if square is empty, returns a Some with the color if not
-fn color_of_square(pos: (u8, u8), pieces: &Vec<Piece>) -> Option<PieceColor> {
-    for piece in pieces {
-        if piece.x == pos.0 && piece.y == pos.1 {
-            return Some(piece.color);
-        }
-    }
-    None
-}
+/// Marks a piece being dragged, which follows the cursor instead of moving to its square
+pub struct Dragged;
 
-fn move_pieces(time: Res<Time>, mut query: Query<(&mut Transform, &Piece)>) {
+fn move_pieces(time: Res<Time>, mut query: Query<(&mut Transform, &Piece), Without<Dragged>>) {
     for (mut transform, piece) in query.iter_mut() {
         // Get the direction to move in
         let direction = Vec3::new(piece.x as f32, 0., piece.y as f32) - transform.translation;
@@ -258,62 +59,19 @@ fn move_pieces(time: Res<Time>, mut query: Query<(&mut Transform, &Piece)>) {
     }
 }
 
-fn create_pieces(commands: &mut Commands, piece_data: Res<PieceData>) {
-    let pieces = vec![
-        (
-            PieceType::Pawn,
-            vec![
-                (PieceColor::White, (0..8).map(|i| (1, i)).collect()),
-                (PieceColor::Black, (0..8).map(|i| (6, i)).collect()),
-            ],
-        ),
-        (
-            PieceType::Rook,
-            vec![
-                (PieceColor::White, vec![(0, 0), (0, 7)]),
-                (PieceColor::Black, vec![(7, 0), (7, 7)]),
-            ],
-        ),
-        (
-            PieceType::Knight,
-            vec![
-                (PieceColor::White, vec![(0, 1), (0, 6)]),
-                (PieceColor::Black, vec![(7, 1), (7, 6)]),
-            ],
-        ),
-        (
-            PieceType::Bishop,
-            vec![
-                (PieceColor::White, vec![(0, 2), (0, 5)]),
-                (PieceColor::Black, vec![(7, 2), (7, 5)]),
-            ],
-        ),
-        (
-            PieceType::Queen,
-            vec![
-                (PieceColor::White, vec![(0, 3)]),
-                (PieceColor::Black, vec![(7, 3)]),
-            ],
-        ),
-        (
-            PieceType::King,
-            vec![
-                (PieceColor::White, vec![(0, 4)]),
-                (PieceColor::Black, vec![(7, 4)]),
-            ],
-        ),
-    ];
+fn create_pieces(commands: &mut Commands, piece_data: Res<PieceData>, position: Res<Position>) {
+    spawn_pieces(commands, &piece_data, &position);
+}
 
-    for piece in pieces {
-        for color in piece.1 {
-            for position in color.1 {
-                spawn_piece(commands, &piece_data, color.0, piece.0, position);
-            }
-        }
+/// Spawns every piece of `position`
+pub fn spawn_pieces(commands: &mut Commands, piece_data: &Res<PieceData>, position: &Position) {
+    // Mirror whatever pieces the rules position holds
+    for (square, color, piece_type) in position.pieces() {
+        spawn_piece(commands, piece_data, color, piece_type, square);
     }
 }
 
-fn spawn_piece(
+pub fn spawn_piece(
     commands: &mut Commands,
     piece_data: &Res<PieceData>,
     piece_color: PieceColor,
@@ -337,44 +95,156 @@ fn spawn_piece(
             y: position.1,
         })
         // Add children to the parent
-        .with_children(|parent| {
-            let (meshes, child_translation) = match piece_type {
-                PieceType::Pawn => (vec![piece_data.pawn_mesh.clone()], Vec3::new(-0.2, 0., 2.6)),
-                PieceType::Rook => (vec![piece_data.rook_mesh.clone()], Vec3::new(-0.1, 0., 1.8)),
-                PieceType::Knight => (
-                    vec![
-                        piece_data.knight_1_mesh.clone(),
-                        piece_data.knight_2_mesh.clone(),
-                    ],
-                    Vec3::new(-0.2, 0., 0.9),
-                ),
-                PieceType::Bishop => (vec![piece_data.bishop_mesh.clone()], Vec3::new(-0.1, 0., 0.)),
-                PieceType::Queen => (vec![piece_data.queen_mesh.clone()], Vec3::new(-0.2, 0., -0.95)),
-                PieceType::King => (
-                    vec![
-                        piece_data.king_1_mesh.clone(),
-                        piece_data.king_2_mesh.clone(),
-                    ],
-                    Vec3::new(-0.2, 0., -1.9),
-                ),
+        .with_children(|parent| spawn_piece_meshes(parent, piece_data, piece_color, piece_type));
+}
+
+/// Marks a stand-in piece showing an earlier position, which the game never moves
+pub struct HistoryPiece;
+
+/// Spawns stand-ins for every piece of `position`
+pub fn spawn_history_pieces(
+    commands: &mut Commands,
+    piece_data: &Res<PieceData>,
+    position: &Position,
+) {
+    for ((x, y), color, piece_type) in position.pieces() {
+        commands
+            .spawn(PbrBundle {
+                transform: Transform::from_translation(Vec3::new(x as f32, 0., y as f32)),
+                ..Default::default()
+            })
+            .with(HistoryPiece)
+            .with_children(|parent| spawn_piece_meshes(parent, piece_data, color, piece_type));
+    }
+}
+
+/// Spawns the meshes making up a piece as children of `parent`
+fn spawn_piece_meshes(
+    parent: &mut ChildBuilder,
+    piece_data: &PieceData,
+    piece_color: PieceColor,
+    piece_type: PieceType,
+) {
+    let (meshes, child_translation) = match piece_type {
+        PieceType::Pawn => (vec![piece_data.pawn_mesh.clone()], Vec3::new(-0.2, 0., 2.6)),
+        PieceType::Rook => (vec![piece_data.rook_mesh.clone()], Vec3::new(-0.1, 0., 1.8)),
+        PieceType::Knight => (
+            vec![
+                piece_data.knight_1_mesh.clone(),
+                piece_data.knight_2_mesh.clone(),
+            ],
+            Vec3::new(-0.2, 0., 0.9),
+        ),
+        PieceType::Bishop => (
+            vec![piece_data.bishop_mesh.clone()],
+            Vec3::new(-0.1, 0., 0.),
+        ),
+        PieceType::Queen => (
+            vec![piece_data.queen_mesh.clone()],
+            Vec3::new(-0.2, 0., -0.95),
+        ),
+        PieceType::King => (
+            vec![
+                piece_data.king_1_mesh.clone(),
+                piece_data.king_2_mesh.clone(),
+            ],
+            Vec3::new(-0.2, 0., -1.9),
+        ),
+    };
+
+    for mesh in meshes {
+        parent.spawn(PbrBundle {
+            mesh: mesh,
+            material: match piece_color {
+                PieceColor::White => piece_data.white_color.clone(),
+                PieceColor::Black => piece_data.black_color.clone(),
+            },
+            transform: {
+                let mut transform = Transform::from_translation(child_translation);
+                transform.apply_non_uniform_scale(Vec3::new(0.2, 0.2, 0.2));
+                transform
+            },
+            ..Default::default()
+        });
+    }
+}
+
+/// Marks a smaller copy of a taken piece, lined up beside the board
+pub struct CapturedPiece;
+
+/// Show the taken pieces beside the board, each side's captures along its own edge
+fn captured_trays(
+    commands: &mut Commands,
+    piece_data: Res<PieceData>,
+    position: Res<Position>,
+    mut shown: Local<Vec<(PieceColor, PieceType)>>,
+    query: Query<Entity, With<CapturedPiece>>,
+) {
+    let mut captured: Vec<_> = position.captured_pieces().collect();
+    // Group by piece, most valuable first
+    captured
+        .sort_by_key(|&(_, piece_type)| (std::cmp::Reverse(piece_type.points()), piece_type as u8));
+    if *shown == captured {
+        return;
+    }
+
+    for entity in query.iter() {
+        commands.despawn_recursive(entity);
+    }
+
+    for &color in [PieceColor::White, PieceColor::Black].iter() {
+        // White's captures go beside the a-file starting from White's end, Black's opposite
+        let taken = captured
+            .iter()
+            .filter(|&&(piece_color, _)| piece_color != color);
+        for (i, &(piece_color, piece_type)) in taken.enumerate() {
+            let translation = match color {
+                PieceColor::White => Vec3::new(i as f32 * 0.5, 0., -1.2),
+                PieceColor::Black => Vec3::new(7. - i as f32 * 0.5, 0., 8.2),
             };
+            let mut transform = Transform::from_translation(translation);
+            transform.scale = Vec3::splat(0.6);
 
-            for mesh in meshes {
-                parent.spawn(PbrBundle {
-                    mesh: mesh,
-                    material: match piece_color {
-                        PieceColor::White => piece_data.white_color.clone(),
-                        PieceColor::Black => piece_data.black_color.clone(),
-                    },
-                    transform: {
-                        let mut transform = Transform::from_translation(child_translation);
-                        transform.apply_non_uniform_scale(Vec3::new(0.2, 0.2, 0.2));
-                        transform
-                    },
+            commands
+                .spawn(PbrBundle {
+                    transform,
                     ..Default::default()
+                })
+                .with(CapturedPiece)
+                .with_children(|parent| {
+                    spawn_piece_meshes(parent, &piece_data, piece_color, piece_type)
                 });
-            }
+        }
+    }
+
+    *shown = captured;
+}
+
+/// Marks a piece whose type changed, so its meshes need replacing
+pub struct Promoted;
+
+fn swap_promoted_meshes(
+    commands: &mut Commands,
+    piece_data: Res<PieceData>,
+    history_view: Res<HistoryView>,
+    query: Query<(Entity, &Piece, &Children), With<Promoted>>,
+) {
+    // The live pieces are hidden while looking back, so wait until they're on show again
+    if history_view.ply.is_some() {
+        return;
+    }
+
+    for (entity, piece, children) in query.iter() {
+        for child in children.iter() {
+            commands.despawn_recursive(*child);
+        }
+
+        commands.set_current_entity(entity);
+        commands.with_children(|parent| {
+            spawn_piece_meshes(parent, &piece_data, piece.color, piece.piece_type)
         });
+        commands.remove_one::<Promoted>(entity);
+    }
 }
 
 pub struct PiecesPlugin;
@@ -382,6 +252,19 @@ impl Plugin for PiecesPlugin {
     fn build(&self, app: &mut AppBuilder) {
         app.init_resource::<PieceData>()
             .add_startup_system(create_pieces.system())
-            .add_system(move_pieces.system());
+            .on_state_update(APP_STATE_STAGE, AppState::Playing, move_pieces.system())
+            .on_state_update(
+                APP_STATE_STAGE,
+                AppState::Playing,
+                swap_promoted_meshes.system(),
+            )
+            .on_state_update(APP_STATE_STAGE, AppState::Playing, captured_trays.system())
+            // Let the last move finish on the game over screen
+            .on_state_update(APP_STATE_STAGE, AppState::GameOver, move_pieces.system())
+            .on_state_update(
+                APP_STATE_STAGE,
+                AppState::GameOver,
+                swap_promoted_meshes.system(),
+            );
     }
 }
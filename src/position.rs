@@ -0,0 +1,871 @@
+use std::fmt;
+
+use crate::{bitboard::*, zobrist::*};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum PieceColor {
+    White,
+    Black,
+}
+
+impl PieceColor {
+    pub fn opposite(self) -> Self {
+        match self {
+            Self::White => Self::Black,
+            Self::Black => Self::White,
+        }
+    }
+
+    /// Direction pawns of this color advance along `x`
+    fn pawn_direction(self) -> i8 {
+        match self {
+            Self::White => 1,
+            Self::Black => -1,
+        }
+    }
+
+    /// Row where pawns of this color start, and may move two squares from
+    fn pawn_row(self) -> u8 {
+        match self {
+            Self::White => 1,
+            Self::Black => 6,
+        }
+    }
+
+    pub(crate) fn index(self) -> usize {
+        self as usize
+    }
+}
+
+impl fmt::Display for PieceColor {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::White => write!(f, "White"),
+            Self::Black => write!(f, "Black"),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum PieceType {
+    King,
+    Queen,
+    Bishop,
+    Knight,
+    Rook,
+    Pawn,
+}
+
+impl PieceType {
+    pub(crate) fn index(self) -> usize {
+        self as usize
+    }
+
+    /// Conventional value in pawns, for counting material. Kings don't count.
+    pub fn points(self) -> u32 {
+        match self {
+            Self::King => 0,
+            Self::Queen => 9,
+            Self::Rook => 5,
+            Self::Bishop | Self::Knight => 3,
+            Self::Pawn => 1,
+        }
+    }
+}
+
+impl fmt::Display for PieceType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::King => write!(f, "King"),
+            Self::Queen => write!(f, "Queen"),
+            Self::Bishop => write!(f, "Bishop"),
+            Self::Knight => write!(f, "Knight"),
+            Self::Rook => write!(f, "Rook"),
+            Self::Pawn => write!(f, "Pawn"),
+        }
+    }
+}
+
+/// Returns the square `(dx, dy)` away from `pos`, or None if it's off the board
+fn offset(pos: (u8, u8), dx: i8, dy: i8) -> Option<(u8, u8)> {
+    let x = pos.0 as i8 + dx;
+    let y = pos.1 as i8 + dy;
+    if (0..8).contains(&x) && (0..8).contains(&y) {
+        Some((x as u8, y as u8))
+    } else {
+        None
+    }
+}
+
+/// Returns the rook's `(from, to)` squares when a king of `piece_type` castles with `mv`
+fn castling_rook_squares(mv: Move, piece_type: PieceType) -> Option<((u8, u8), (u8, u8))> {
+    if piece_type != PieceType::King || (mv.to.1 as i8 - mv.from.1 as i8).abs() != 2 {
+        return None;
+    }
+
+    let row = mv.from.0;
+    if mv.to.1 > mv.from.1 {
+        Some(((row, 7), (row, 5)))
+    } else {
+        Some(((row, 0), (row, 3)))
+    }
+}
+
+/// Returns the algebraic name of a square, like `e4`
+pub fn square_name(pos: (u8, u8)) -> String {
+    format!("{}{}", (b'a' + pos.1) as char, pos.0 + 1)
+}
+
+/// Parses an algebraic square name like `e4`
+pub fn parse_square(name: &str) -> Option<(u8, u8)> {
+    let bytes = name.as_bytes();
+    if bytes.len() != 2 {
+        return None;
+    }
+
+    let y = bytes[0].wrapping_sub(b'a');
+    let x = bytes[1].wrapping_sub(b'1');
+    if x < 8 && y < 8 {
+        Some((x, y))
+    } else {
+        None
+    }
+}
+
+/// Pieces a pawn can be promoted to, best first
+pub const PROMOTION_TYPES: [PieceType; 4] = [
+    PieceType::Queen,
+    PieceType::Rook,
+    PieceType::Bishop,
+    PieceType::Knight,
+];
+
+/// Adds a pawn move, expanded into every promotion if it reaches the last row
+fn push_pawn_move(mv: Move, moves: &mut Vec<Move>) {
+    if mv.to.0 == 0 || mv.to.0 == 7 {
+        for &piece_type in PROMOTION_TYPES.iter() {
+            moves.push(Move {
+                promotion: Some(piece_type),
+                ..mv
+            });
+        }
+    } else {
+        moves.push(mv);
+    }
+}
+
+/// A move between two `(x, y)` squares, using the same coordinates as `Piece`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Move {
+    pub from: (u8, u8),
+    pub to: (u8, u8),
+    pub promotion: Option<PieceType>,
+}
+impl Move {
+    pub fn new(from: (u8, u8), to: (u8, u8)) -> Self {
+        Self {
+            from,
+            to,
+            promotion: None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct CastlingRights {
+    pub white_king_side: bool,
+    pub white_queen_side: bool,
+    pub black_king_side: bool,
+    pub black_queen_side: bool,
+}
+impl CastlingRights {
+    pub const ALL: Self = Self {
+        white_king_side: true,
+        white_queen_side: true,
+        black_king_side: true,
+        black_queen_side: true,
+    };
+
+    /// Clears any right that depends on the piece standing on `pos`
+    fn touch(&mut self, pos: (u8, u8)) {
+        match pos {
+            (0, 4) => {
+                self.white_king_side = false;
+                self.white_queen_side = false;
+            }
+            (7, 4) => {
+                self.black_king_side = false;
+                self.black_queen_side = false;
+            }
+            (0, 0) => self.white_queen_side = false,
+            (0, 7) => self.white_king_side = false,
+            (7, 0) => self.black_queen_side = false,
+            (7, 7) => self.black_king_side = false,
+            _ => {}
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GameResult {
+    Win(PieceColor),
+    Draw,
+}
+
+impl fmt::Display for GameResult {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Win(color) => write!(f, "{} won", color),
+            Self::Draw => write!(f, "Draw"),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GameEndReason {
+    Checkmate,
+    Stalemate,
+    FiftyMoveRule,
+    ThreefoldRepetition,
+    InsufficientMaterial,
+    /// Running out of time
+    Timeout,
+    /// Running out of time when the opponent couldn't have mated anyway
+    TimeoutVsInsufficientMaterial,
+}
+
+impl fmt::Display for GameEndReason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Checkmate => write!(f, "checkmate"),
+            Self::Stalemate => write!(f, "stalemate"),
+            Self::FiftyMoveRule => write!(f, "fifty-move rule"),
+            Self::ThreefoldRepetition => write!(f, "threefold repetition"),
+            Self::InsufficientMaterial => write!(f, "insufficient material"),
+            Self::Timeout => write!(f, "timeout"),
+            Self::TimeoutVsInsufficientMaterial => {
+                write!(f, "timeout vs insufficient material")
+            }
+        }
+    }
+}
+
+/// How and why a game finished
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct GameOver {
+    pub result: GameResult,
+    pub reason: GameEndReason,
+}
+
+impl fmt::Display for GameOver {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} by {}", self.result, self.reason)
+    }
+}
+
+/// Everything `make_move` overwrites, so `unmake_move` can put it back
+#[derive(Clone, Copy)]
+struct Undo {
+    hash: u64,
+    mv: Move,
+    moved: (PieceColor, PieceType),
+    captured: Option<((u8, u8), (PieceColor, PieceType))>,
+    castling: CastlingRights,
+    en_passant: Option<(u8, u8)>,
+    halfmove_clock: u32,
+}
+
+/// Board state and rules, independent of the ECS.
+///
+/// Squares are `(x, y)` pairs where `x` is the row (White starts on rows 0 and 1)
+/// and `y` is the column, matching `Piece::x` and `Piece::y`.
+#[derive(Clone)]
+pub struct Position {
+    board: [[Option<(PieceColor, PieceType)>; 8]; 8],
+    /// The same pieces as `board`, as bitboards by color and by type
+    colors: [Bitboard; 2],
+    piece_types: [Bitboard; 6],
+    /// Zobrist hash of the pieces, side to move and castling rights, kept up to date as
+    /// they change. En passant is added in `hash`.
+    hash: u64,
+    side_to_move: PieceColor,
+    castling: CastlingRights,
+    en_passant: Option<(u8, u8)>,
+    halfmove_clock: u32,
+    fullmove_number: u32,
+    history: Vec<Undo>,
+}
+
+impl Default for Position {
+    /// The standard starting position
+    fn default() -> Self {
+        let mut position = Self::empty();
+        let back_row = [
+            PieceType::Rook,
+            PieceType::Knight,
+            PieceType::Bishop,
+            PieceType::Queen,
+            PieceType::King,
+            PieceType::Bishop,
+            PieceType::Knight,
+            PieceType::Rook,
+        ];
+
+        for (y, piece_type) in back_row.iter().enumerate() {
+            let y = y as u8;
+            position.set_piece((0, y), Some((PieceColor::White, *piece_type)));
+            position.set_piece((1, y), Some((PieceColor::White, PieceType::Pawn)));
+            position.set_piece((6, y), Some((PieceColor::Black, PieceType::Pawn)));
+            position.set_piece((7, y), Some((PieceColor::Black, *piece_type)));
+        }
+        position.set_castling_rights(CastlingRights::ALL);
+
+        position
+    }
+}
+
+impl Position {
+    /// An empty board with White to move and no castling rights
+    pub fn empty() -> Self {
+        Self {
+            board: [[None; 8]; 8],
+            colors: [0; 2],
+            piece_types: [0; 6],
+            hash: 0,
+            side_to_move: PieceColor::White,
+            castling: CastlingRights::default(),
+            en_passant: None,
+            halfmove_clock: 0,
+            fullmove_number: 1,
+            history: Vec::new(),
+        }
+    }
+
+    pub fn piece_at(&self, pos: (u8, u8)) -> Option<(PieceColor, PieceType)> {
+        self.board[pos.0 as usize][pos.1 as usize]
+    }
+
+    /// Puts a piece on (or clears) a square without touching any other state
+    pub fn set_piece(&mut self, pos: (u8, u8), piece: Option<(PieceColor, PieceType)>) {
+        let bit = square_bit(pos);
+        if let Some((color, piece_type)) = self.piece_at(pos) {
+            self.colors[color.index()] &= !bit;
+            self.piece_types[piece_type.index()] &= !bit;
+            self.hash ^= piece_key(color, piece_type, pos);
+        }
+        if let Some((color, piece_type)) = piece {
+            self.colors[color.index()] |= bit;
+            self.piece_types[piece_type.index()] |= bit;
+            self.hash ^= piece_key(color, piece_type, pos);
+        }
+        self.board[pos.0 as usize][pos.1 as usize] = piece;
+    }
+
+    /// All pieces on the board along with their squares
+    pub fn pieces(&self) -> impl Iterator<Item = ((u8, u8), PieceColor, PieceType)> + '_ {
+        Squares(self.occupied()).filter_map(move |pos| {
+            self.piece_at(pos)
+                .map(|(color, piece_type)| (pos, color, piece_type))
+        })
+    }
+
+    /// Squares holding any piece
+    pub fn occupied(&self) -> Bitboard {
+        self.colors[0] | self.colors[1]
+    }
+
+    /// Squares holding `color`'s pieces of type `piece_type`
+    pub fn pieces_of(&self, color: PieceColor, piece_type: PieceType) -> Bitboard {
+        self.colors[color.index()] & self.piece_types[piece_type.index()]
+    }
+
+    /// Total points of `color`'s pieces on the board
+    pub fn material(&self, color: PieceColor) -> u32 {
+        self.pieces()
+            .filter(|&(_, piece_color, _)| piece_color == color)
+            .map(|(_, _, piece_type)| piece_type.points())
+            .sum()
+    }
+
+    pub fn side_to_move(&self) -> PieceColor {
+        self.side_to_move
+    }
+
+    pub fn set_side_to_move(&mut self, color: PieceColor) {
+        if color != self.side_to_move {
+            self.hash ^= side_key();
+        }
+        self.side_to_move = color;
+    }
+
+    pub fn castling_rights(&self) -> CastlingRights {
+        self.castling
+    }
+
+    pub fn set_castling_rights(&mut self, castling: CastlingRights) {
+        self.hash ^= castling_key(self.castling) ^ castling_key(castling);
+        self.castling = castling;
+    }
+
+    /// Square a pawn skipped over with a double step on the previous move
+    pub fn en_passant(&self) -> Option<(u8, u8)> {
+        self.en_passant
+    }
+
+    pub fn set_en_passant(&mut self, en_passant: Option<(u8, u8)>) {
+        self.en_passant = en_passant;
+    }
+
+    /// Moves since the last capture or pawn move
+    pub fn halfmove_clock(&self) -> u32 {
+        self.halfmove_clock
+    }
+
+    pub fn set_halfmove_clock(&mut self, halfmove_clock: u32) {
+        self.halfmove_clock = halfmove_clock;
+    }
+
+    /// Starts at 1 and increases after every Black move
+    pub fn fullmove_number(&self) -> u32 {
+        self.fullmove_number
+    }
+
+    pub fn set_fullmove_number(&mut self, fullmove_number: u32) {
+        self.fullmove_number = fullmove_number;
+    }
+
+    /// Returns the moves available to the side to move that don't leave its king in check
+    pub fn legal_moves(&self) -> Vec<Move> {
+        self.filter_legal(self.pseudo_legal_moves())
+    }
+
+    /// Returns the legal moves for the piece on `pos`, if it's its turn
+    pub fn legal_moves_from(&self, pos: (u8, u8)) -> Vec<Move> {
+        let mut moves = Vec::new();
+        if let Some((color, piece_type)) = self.piece_at(pos) {
+            if color == self.side_to_move {
+                self.piece_moves(pos, color, piece_type, &mut moves);
+            }
+        }
+        self.filter_legal(moves)
+    }
+
+    /// Returns every move the side to move's pieces can make, ignoring checks
+    pub fn pseudo_legal_moves(&self) -> Vec<Move> {
+        let color = self.side_to_move;
+        let mut moves = Vec::with_capacity(64);
+        for pos in Squares(self.colors[color.index()]) {
+            if let Some((_, piece_type)) = self.piece_at(pos) {
+                self.piece_moves(pos, color, piece_type, &mut moves);
+            }
+        }
+        moves
+    }
+
+    /// Drops the moves that would leave the mover's own king attacked
+    fn filter_legal(&self, mut moves: Vec<Move>) -> Vec<Move> {
+        moves.retain(|&mv| self.is_legal(mv));
+        moves
+    }
+
+    /// Whether a pseudo-legal move keeps the mover's king safe, found by looking at the
+    /// board as it would be after the move rather than playing it
+    fn is_legal(&self, mv: Move) -> bool {
+        let color = self.side_to_move;
+        let piece_type = match self.piece_at(mv.from) {
+            Some((_, piece_type)) => piece_type,
+            None => return false,
+        };
+        let king = if piece_type == PieceType::King {
+            mv.to
+        } else {
+            match self.king_square(color) {
+                Some(king) => king,
+                None => return true,
+            }
+        };
+
+        let captured = self.capture_square(mv).map_or(0, square_bit);
+        let mut occupied = (self.occupied() & !square_bit(mv.from) & !captured) | square_bit(mv.to);
+        if let Some((rook_from, rook_to)) = castling_rook_squares(mv, piece_type) {
+            occupied ^= square_bit(rook_from) | square_bit(rook_to);
+        }
+
+        !self.is_attacked_with(king, color.opposite(), occupied, captured)
+    }
+
+    /// Returns the square of `color`'s king, if it has one
+    pub fn king_square(&self, color: PieceColor) -> Option<(u8, u8)> {
+        Squares(self.pieces_of(color, PieceType::King)).next()
+    }
+
+    /// Whether `color`'s king is currently attacked
+    pub fn is_in_check(&self, color: PieceColor) -> bool {
+        self.king_square(color)
+            .is_some_and(|pos| self.is_square_attacked(pos, color.opposite()))
+    }
+
+    /// Whether any piece of color `by` attacks `pos`
+    pub fn is_square_attacked(&self, pos: (u8, u8), by: PieceColor) -> bool {
+        self.is_attacked_with(pos, by, self.occupied(), 0)
+    }
+
+    /// Whether `by` attacks `pos` if the board had `occupied` squares and `by` lost the
+    /// pieces on `removed`
+    fn is_attacked_with(
+        &self,
+        pos: (u8, u8),
+        by: PieceColor,
+        occupied: Bitboard,
+        removed: Bitboard,
+    ) -> bool {
+        let attackers = |piece_type| self.pieces_of(by, piece_type) & !removed;
+        let queens = attackers(PieceType::Queen);
+
+        // Pawns attack `pos` from the squares a pawn of the other color on `pos` would attack
+        pawn_attacks(pos, by.opposite()) & attackers(PieceType::Pawn) != 0
+            || knight_attacks(pos) & attackers(PieceType::Knight) != 0
+            || king_attacks(pos) & attackers(PieceType::King) != 0
+            || rook_attacks(pos, occupied) & (attackers(PieceType::Rook) | queens) != 0
+            || bishop_attacks(pos, occupied) & (attackers(PieceType::Bishop) | queens) != 0
+    }
+
+    /// Returns the square of the piece `mv` would capture, if any
+    pub fn capture_square(&self, mv: Move) -> Option<(u8, u8)> {
+        if self.piece_at(mv.to).is_some() {
+            return Some(mv.to);
+        }
+
+        // En passant takes the pawn that skipped past the destination square
+        match self.piece_at(mv.from) {
+            Some((_, PieceType::Pawn))
+                if mv.from.1 != mv.to.1 && self.en_passant == Some(mv.to) =>
+            {
+                Some((mv.from.0, mv.to.1))
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns the last move played with `make_move`
+    pub fn last_move(&self) -> Option<Move> {
+        self.history.last().map(|undo| undo.mv)
+    }
+
+    /// Pieces taken by the moves played with `make_move`, oldest first
+    pub fn captured_pieces(&self) -> impl Iterator<Item = (PieceColor, PieceType)> + '_ {
+        self.history
+            .iter()
+            .filter_map(|undo| undo.captured.map(|(_, piece)| piece))
+    }
+
+    /// Returns where the rook goes `(from, to)` if `mv` is a castling move
+    pub fn castling_rook_move(&self, mv: Move) -> Option<((u8, u8), (u8, u8))> {
+        self.piece_at(mv.from)
+            .and_then(|(_, piece_type)| castling_rook_squares(mv, piece_type))
+    }
+
+    fn piece_moves(
+        &self,
+        pos: (u8, u8),
+        color: PieceColor,
+        piece_type: PieceType,
+        moves: &mut Vec<Move>,
+    ) {
+        let occupied = self.occupied();
+        let targets = match piece_type {
+            PieceType::King => {
+                self.castling_moves(pos, color, moves);
+                king_attacks(pos)
+            }
+            PieceType::Knight => knight_attacks(pos),
+            PieceType::Queen => queen_attacks(pos, occupied),
+            PieceType::Rook => rook_attacks(pos, occupied),
+            PieceType::Bishop => bishop_attacks(pos, occupied),
+            PieceType::Pawn => return self.pawn_moves(pos, color, moves),
+        };
+
+        for to in Squares(targets & !self.colors[color.index()]) {
+            moves.push(Move::new(pos, to));
+        }
+    }
+
+    fn castling_moves(&self, pos: (u8, u8), color: PieceColor, moves: &mut Vec<Move>) {
+        let row = match color {
+            PieceColor::White => 0,
+            PieceColor::Black => 7,
+        };
+        let (king_side, queen_side) = match color {
+            PieceColor::White => (
+                self.castling.white_king_side,
+                self.castling.white_queen_side,
+            ),
+            PieceColor::Black => (
+                self.castling.black_king_side,
+                self.castling.black_queen_side,
+            ),
+        };
+        if pos != (row, 4) || !(king_side || queen_side) {
+            return;
+        }
+
+        // (allowed, rook column, columns that must be empty, columns the king crosses)
+        let sides: [(bool, u8, &[u8], &[u8]); 2] = [
+            (king_side, 7, &[5, 6], &[4, 5, 6]),
+            (queen_side, 0, &[1, 2, 3], &[4, 3, 2]),
+        ];
+        for &(allowed, rook_y, empty, crossed) in sides.iter() {
+            if allowed
+                && self.piece_at((row, rook_y)) == Some((color, PieceType::Rook))
+                && empty.iter().all(|&y| self.piece_at((row, y)).is_none())
+                && !crossed
+                    .iter()
+                    .any(|&y| self.is_square_attacked((row, y), color.opposite()))
+            {
+                moves.push(Move::new(pos, (row, crossed[2])));
+            }
+        }
+    }
+
+    fn pawn_moves(&self, pos: (u8, u8), color: PieceColor, moves: &mut Vec<Move>) {
+        let direction = color.pawn_direction();
+
+        // Normal move, and move 2 squares from the starting row
+        if let Some(to) = offset(pos, direction, 0) {
+            if self.piece_at(to).is_none() {
+                push_pawn_move(Move::new(pos, to), moves);
+
+                if pos.0 == color.pawn_row() {
+                    if let Some(to) = offset(to, direction, 0) {
+                        if self.piece_at(to).is_none() {
+                            moves.push(Move::new(pos, to));
+                        }
+                    }
+                }
+            }
+        }
+
+        // Take piece
+        let mut targets = self.colors[color.opposite().index()];
+        if let Some(en_passant) = self.en_passant {
+            targets |= square_bit(en_passant);
+        }
+        for to in Squares(pawn_attacks(pos, color) & targets) {
+            push_pawn_move(Move::new(pos, to), moves);
+        }
+    }
+
+    /// Returns how the game ended, if it has
+    pub fn game_over(&self) -> Option<GameOver> {
+        if self.legal_moves().is_empty() {
+            return Some(if self.is_in_check(self.side_to_move) {
+                GameOver {
+                    result: GameResult::Win(self.side_to_move.opposite()),
+                    reason: GameEndReason::Checkmate,
+                }
+            } else {
+                GameOver {
+                    result: GameResult::Draw,
+                    reason: GameEndReason::Stalemate,
+                }
+            });
+        }
+
+        let reason = if self.is_insufficient_material() {
+            GameEndReason::InsufficientMaterial
+        } else if self.repetition_count() >= 3 {
+            GameEndReason::ThreefoldRepetition
+        } else if self.halfmove_clock >= 100 {
+            GameEndReason::FiftyMoveRule
+        } else {
+            return None;
+        };
+
+        Some(GameOver {
+            result: GameResult::Draw,
+            reason,
+        })
+    }
+
+    /// How many times the current position has occurred, counting this one
+    pub fn repetition_count(&self) -> usize {
+        let hash = self.hash();
+
+        // Positions before the last capture or pawn move can't come back
+        1 + self
+            .history
+            .iter()
+            .rev()
+            .take(self.halfmove_clock as usize)
+            .filter(|undo| undo.hash == hash)
+            .count()
+    }
+
+    /// How the game ends when `flagged` runs out of time. It's a draw if the opponent
+    /// couldn't checkmate with the pieces they have left.
+    pub fn timeout(&self, flagged: PieceColor) -> GameOver {
+        if self.can_checkmate(flagged.opposite()) {
+            GameOver {
+                result: GameResult::Win(flagged.opposite()),
+                reason: GameEndReason::Timeout,
+            }
+        } else {
+            GameOver {
+                result: GameResult::Draw,
+                reason: GameEndReason::TimeoutVsInsufficientMaterial,
+            }
+        }
+    }
+
+    /// Whether `color` has the pieces to checkmate by any series of legal moves
+    pub fn can_checkmate(&self, color: PieceColor) -> bool {
+        let has = |piece_type| self.pieces_of(color, piece_type) != 0;
+        if has(PieceType::Pawn) || has(PieceType::Rook) || has(PieceType::Queen) {
+            return true;
+        }
+
+        let knights = self.pieces_of(color, PieceType::Knight);
+        let bishops = self.pieces_of(color, PieceType::Bishop);
+        let same_colored_bishops = bishops & LIGHT_SQUARES == 0 || bishops & !LIGHT_SQUARES == 0;
+        if knights | bishops == 0 {
+            return false;
+        }
+
+        // A lone minor piece, or bishops all on one square color, can only mate when the
+        // other side's own pieces hem in its king
+        let opponent = color.opposite();
+        let opponent_has_pieces =
+            self.colors[opponent.index()] & !self.pieces_of(opponent, PieceType::King) != 0;
+        if (knights | bishops).count_ones() == 1 || (knights == 0 && same_colored_bishops) {
+            return opponent_has_pieces;
+        }
+        true
+    }
+
+    /// Whether neither side has enough pieces left to checkmate
+    pub fn is_insufficient_material(&self) -> bool {
+        let mut minor_pieces = 0;
+        let mut bishop_square_colors = Vec::new();
+
+        for (pos, _, piece_type) in self.pieces() {
+            match piece_type {
+                PieceType::King => {}
+                PieceType::Knight => minor_pieces += 1,
+                PieceType::Bishop => {
+                    minor_pieces += 1;
+                    bishop_square_colors.push((pos.0 + pos.1) % 2);
+                }
+                _ => return false,
+            }
+        }
+
+        // K vs K and K+minor vs K, or any number of bishops all on the same square color
+        minor_pieces <= 1
+            || (minor_pieces == bishop_square_colors.len()
+                && bishop_square_colors
+                    .iter()
+                    .all(|&color| color == bishop_square_colors[0]))
+    }
+
+    /// Zobrist hash of the position. Positions that count as repeats for the draw rules
+    /// hash the same.
+    pub fn hash(&self) -> u64 {
+        match self.en_passant {
+            Some(square) if self.can_capture_en_passant(square) => {
+                self.hash ^ en_passant_key(square.1)
+            }
+            _ => self.hash,
+        }
+    }
+
+    /// Whether a pawn of the side to move stands next to the pawn that skipped `square`.
+    /// The en passant square only makes positions differ when this is true.
+    fn can_capture_en_passant(&self, square: (u8, u8)) -> bool {
+        let color = self.side_to_move;
+        [-1, 1].iter().any(|&dy| {
+            offset(square, -color.pawn_direction(), dy).and_then(|pos| self.piece_at(pos))
+                == Some((color, PieceType::Pawn))
+        })
+    }
+
+    /// Plays `mv` for the side to move. The move is assumed to be legal.
+    pub fn make_move(&mut self, mv: Move) {
+        let moved = self
+            .piece_at(mv.from)
+            .expect("make_move called without a piece on the starting square");
+        let captured = self
+            .capture_square(mv)
+            .and_then(|square| self.piece_at(square).map(|piece| (square, piece)));
+
+        self.history.push(Undo {
+            hash: self.hash(),
+            mv,
+            moved,
+            captured,
+            castling: self.castling,
+            en_passant: self.en_passant,
+            halfmove_clock: self.halfmove_clock,
+        });
+
+        let (color, piece_type) = moved;
+        if let Some((square, _)) = captured {
+            self.set_piece(square, None);
+        }
+        self.set_piece(mv.from, None);
+        self.set_piece(mv.to, Some((color, mv.promotion.unwrap_or(piece_type))));
+        if let Some((rook_from, rook_to)) = castling_rook_squares(mv, piece_type) {
+            let rook = self.piece_at(rook_from);
+            self.set_piece(rook_from, None);
+            self.set_piece(rook_to, rook);
+        }
+
+        let mut castling = self.castling;
+        castling.touch(mv.from);
+        castling.touch(mv.to);
+        self.set_castling_rights(castling);
+
+        self.en_passant =
+            if piece_type == PieceType::Pawn && (mv.to.0 as i8 - mv.from.0 as i8).abs() == 2 {
+                Some(((mv.from.0 + mv.to.0) / 2, mv.from.1))
+            } else {
+                None
+            };
+
+        if piece_type == PieceType::Pawn || captured.is_some() {
+            self.halfmove_clock = 0;
+        } else {
+            self.halfmove_clock += 1;
+        }
+        if color == PieceColor::Black {
+            self.fullmove_number += 1;
+        }
+        self.set_side_to_move(color.opposite());
+    }
+
+    /// Takes back the last move played with `make_move`, returning it
+    pub fn unmake_move(&mut self) -> Option<Move> {
+        let undo = self.history.pop()?;
+        let mv = undo.mv;
+
+        self.set_piece(mv.to, None);
+        self.set_piece(mv.from, Some(undo.moved));
+        if let Some((square, piece)) = undo.captured {
+            self.set_piece(square, Some(piece));
+        }
+        if let Some((rook_from, rook_to)) = castling_rook_squares(mv, undo.moved.1) {
+            let rook = self.piece_at(rook_to);
+            self.set_piece(rook_to, None);
+            self.set_piece(rook_from, rook);
+        }
+
+        self.set_castling_rights(undo.castling);
+        self.en_passant = undo.en_passant;
+        self.halfmove_clock = undo.halfmove_clock;
+        if undo.moved.0 == PieceColor::Black {
+            self.fullmove_number -= 1;
+        }
+        self.set_side_to_move(undo.moved.0);
+
+        Some(mv)
+    }
+}
@@ -0,0 +1,159 @@
+use crate::{fen::piece_to_char, position::*};
+
+impl Position {
+    /// Writes `mv` in Standard Algebraic Notation. The move must be legal in this position.
+    pub fn to_san(&self, mv: Move) -> String {
+        let (color, piece_type) = self
+            .piece_at(mv.from)
+            .expect("to_san called without a piece on the starting square");
+        let mut san = String::new();
+
+        if self.castling_rook_move(mv).is_some() {
+            san.push_str(if mv.to.1 > mv.from.1 { "O-O" } else { "O-O-O" });
+        } else {
+            let is_capture = self.capture_square(mv).is_some();
+
+            if piece_type == PieceType::Pawn {
+                if is_capture {
+                    san.push(file_char(mv.from.1));
+                }
+            } else {
+                san.push(piece_to_char(PieceColor::White, piece_type));
+                san.push_str(&self.disambiguation(mv, piece_type));
+            }
+
+            if is_capture {
+                san.push('x');
+            }
+            san.push_str(&square_name(mv.to));
+
+            if let Some(promotion) = mv.promotion {
+                san.push('=');
+                san.push(piece_to_char(PieceColor::White, promotion));
+            }
+        }
+
+        let mut after = self.clone();
+        after.make_move(mv);
+        if after.is_in_check(color.opposite()) {
+            san.push(if after.legal_moves().is_empty() {
+                '#'
+            } else {
+                '+'
+            });
+        }
+
+        san
+    }
+
+    /// Finds the legal move written as `san`. Check marks and annotations like `!?` are ignored.
+    pub fn parse_san(&self, san: &str) -> Option<Move> {
+        let san = san.trim_end_matches(|c| "+#!?".contains(c));
+
+        // Castling is written as the king moving two squares
+        let castling_direction = match san {
+            "O-O" | "0-0" => Some(2),
+            "O-O-O" | "0-0-0" => Some(-2),
+            _ => None,
+        };
+        if let Some(direction) = castling_direction {
+            return self.legal_moves().into_iter().find(|mv| {
+                self.piece_at(mv.from).map(|(_, t)| t) == Some(PieceType::King)
+                    && mv.to.1 as i8 - mv.from.1 as i8 == direction
+            });
+        }
+
+        let (san, promotion) = match san.find('=') {
+            Some(i) => (
+                &san[..i],
+                Some(piece_from_letter(san[i + 1..].chars().next()?)?),
+            ),
+            None => match san.chars().last().and_then(piece_from_letter) {
+                // Some writers leave out the `=`
+                Some(promotion) if san.len() > 2 => (&san[..san.len() - 1], Some(promotion)),
+                _ => (san, None),
+            },
+        };
+
+        let mut chars: Vec<char> = san.chars().filter(|&c| c != 'x' && c != '-').collect();
+        let piece_type = match chars.first().copied().and_then(piece_from_letter) {
+            Some(piece_type) => {
+                chars.remove(0);
+                piece_type
+            }
+            None => PieceType::Pawn,
+        };
+        if chars.len() < 2 {
+            return None;
+        }
+        let to: String = chars[chars.len() - 2..].iter().collect();
+        let to = parse_square(&to)?;
+
+        // Whatever is left between the piece and the destination disambiguates it
+        let mut file = None;
+        let mut rank = None;
+        for &c in &chars[..chars.len() - 2] {
+            match c {
+                'a'..='h' => file = Some(c as u8 - b'a'),
+                '1'..='8' => rank = Some(c as u8 - b'1'),
+                _ => return None,
+            }
+        }
+
+        let mut candidates = self.legal_moves().into_iter().filter(|mv| {
+            mv.to == to
+                && mv.promotion == promotion
+                && self.piece_at(mv.from).map(|(_, t)| t) == Some(piece_type)
+                && file.is_none_or(|y| mv.from.1 == y)
+                && rank.is_none_or(|x| mv.from.0 == x)
+        });
+
+        match (candidates.next(), candidates.next()) {
+            (Some(mv), None) => Some(mv),
+            _ => None,
+        }
+    }
+
+    /// Returns the file and/or rank needed to tell `mv` apart from the same kind of piece's moves
+    fn disambiguation(&self, mv: Move, piece_type: PieceType) -> String {
+        let others: Vec<(u8, u8)> = self
+            .legal_moves()
+            .into_iter()
+            .filter(|other| {
+                other.to == mv.to
+                    && other.from != mv.from
+                    && self.piece_at(other.from).map(|(_, t)| t) == Some(piece_type)
+            })
+            .map(|other| other.from)
+            .collect();
+
+        if others.is_empty() {
+            String::new()
+        } else if others.iter().all(|from| from.1 != mv.from.1) {
+            file_char(mv.from.1).to_string()
+        } else if others.iter().all(|from| from.0 != mv.from.0) {
+            rank_char(mv.from.0).to_string()
+        } else {
+            square_name(mv.from)
+        }
+    }
+}
+
+fn piece_from_letter(c: char) -> Option<PieceType> {
+    match c {
+        'K' => Some(PieceType::King),
+        'Q' => Some(PieceType::Queen),
+        'R' => Some(PieceType::Rook),
+        'B' => Some(PieceType::Bishop),
+        'N' => Some(PieceType::Knight),
+        _ => None,
+    }
+}
+
+fn file_char(y: u8) -> char {
+    (b'a' + y) as char
+}
+
+fn rank_char(x: u8) -> char {
+    (b'1' + x) as char
+}
@@ -0,0 +1,432 @@
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+use crate::{position::*, transposition::*};
+
+/// Score for delivering mate right away. Mates further away score a little less.
+pub const MATE_SCORE: i32 = 100_000;
+pub const MAX_DEPTH: u32 = 64;
+
+/// Whether `score` means one side can force mate
+pub fn is_mate_score(score: i32) -> bool {
+    score.abs() > MATE_SCORE - MAX_DEPTH as i32 * 2
+}
+
+pub fn piece_value(piece_type: PieceType) -> i32 {
+    match piece_type {
+        PieceType::Pawn => 100,
+        PieceType::Knight => 320,
+        PieceType::Bishop => 330,
+        PieceType::Rook => 500,
+        PieceType::Queen => 900,
+        PieceType::King => 0,
+    }
+}
+
+// Piece-square tables from White's side, with the 8th rank first
+#[rustfmt::skip]
+const PAWN_TABLE: [i32; 64] = [
+     0,  0,  0,  0,  0,  0,  0,  0,
+    50, 50, 50, 50, 50, 50, 50, 50,
+    10, 10, 20, 30, 30, 20, 10, 10,
+     5,  5, 10, 25, 25, 10,  5,  5,
+     0,  0,  0, 20, 20,  0,  0,  0,
+     5, -5,-10,  0,  0,-10, -5,  5,
+     5, 10, 10,-20,-20, 10, 10,  5,
+     0,  0,  0,  0,  0,  0,  0,  0,
+];
+#[rustfmt::skip]
+const KNIGHT_TABLE: [i32; 64] = [
+    -50,-40,-30,-30,-30,-30,-40,-50,
+    -40,-20,  0,  0,  0,  0,-20,-40,
+    -30,  0, 10, 15, 15, 10,  0,-30,
+    -30,  5, 15, 20, 20, 15,  5,-30,
+    -30,  0, 15, 20, 20, 15,  0,-30,
+    -30,  5, 10, 15, 15, 10,  5,-30,
+    -40,-20,  0,  5,  5,  0,-20,-40,
+    -50,-40,-30,-30,-30,-30,-40,-50,
+];
+#[rustfmt::skip]
+const BISHOP_TABLE: [i32; 64] = [
+    -20,-10,-10,-10,-10,-10,-10,-20,
+    -10,  0,  0,  0,  0,  0,  0,-10,
+    -10,  0,  5, 10, 10,  5,  0,-10,
+    -10,  5,  5, 10, 10,  5,  5,-10,
+    -10,  0, 10, 10, 10, 10,  0,-10,
+    -10, 10, 10, 10, 10, 10, 10,-10,
+    -10,  5,  0,  0,  0,  0,  5,-10,
+    -20,-10,-10,-10,-10,-10,-10,-20,
+];
+#[rustfmt::skip]
+const ROOK_TABLE: [i32; 64] = [
+     0,  0,  0,  0,  0,  0,  0,  0,
+     5, 10, 10, 10, 10, 10, 10,  5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+     0,  0,  0,  5,  5,  0,  0,  0,
+];
+#[rustfmt::skip]
+const QUEEN_TABLE: [i32; 64] = [
+    -20,-10,-10, -5, -5,-10,-10,-20,
+    -10,  0,  0,  0,  0,  0,  0,-10,
+    -10,  0,  5,  5,  5,  5,  0,-10,
+     -5,  0,  5,  5,  5,  5,  0, -5,
+      0,  0,  5,  5,  5,  5,  0, -5,
+    -10,  5,  5,  5,  5,  5,  0,-10,
+    -10,  0,  5,  0,  0,  0,  0,-10,
+    -20,-10,-10, -5, -5,-10,-10,-20,
+];
+#[rustfmt::skip]
+const KING_TABLE: [i32; 64] = [
+    -30,-40,-40,-50,-50,-40,-40,-30,
+    -30,-40,-40,-50,-50,-40,-40,-30,
+    -30,-40,-40,-50,-50,-40,-40,-30,
+    -30,-40,-40,-50,-50,-40,-40,-30,
+    -20,-30,-30,-40,-40,-30,-30,-20,
+    -10,-20,-20,-20,-20,-20,-20,-10,
+     20, 20,  0,  0,  0,  0, 20, 20,
+     20, 30, 10,  0,  0, 10, 30, 20,
+];
+
+fn square_bonus(pos: (u8, u8), color: PieceColor, piece_type: PieceType) -> i32 {
+    let table = match piece_type {
+        PieceType::Pawn => &PAWN_TABLE,
+        PieceType::Knight => &KNIGHT_TABLE,
+        PieceType::Bishop => &BISHOP_TABLE,
+        PieceType::Rook => &ROOK_TABLE,
+        PieceType::Queen => &QUEEN_TABLE,
+        PieceType::King => &KING_TABLE,
+    };
+    // Black reads the table upside down
+    let row = match color {
+        PieceColor::White => 7 - pos.0,
+        PieceColor::Black => pos.0,
+    };
+    table[row as usize * 8 + pos.1 as usize]
+}
+
+/// Material and piece-square score, from the side to move's point of view
+pub fn evaluate(position: &Position) -> i32 {
+    let score: i32 = position
+        .pieces()
+        .map(|(pos, color, piece_type)| {
+            let value = piece_value(piece_type) + square_bonus(pos, color, piece_type);
+            match color {
+                PieceColor::White => value,
+                PieceColor::Black => -value,
+            }
+        })
+        .sum();
+
+    match position.side_to_move() {
+        PieceColor::White => score,
+        PieceColor::Black => -score,
+    }
+}
+
+/// When a search has to stop. It always finishes depth 1, so there's a move to play.
+#[derive(Clone, Default)]
+pub struct SearchLimits {
+    /// Deepest iteration to search, or `MAX_DEPTH` if None
+    pub depth: Option<u32>,
+    pub time: Option<Duration>,
+    /// Set from another thread to stop searching early
+    pub stop: Option<Arc<AtomicBool>>,
+}
+
+/// Result of one finished iteration
+#[derive(Clone, Debug)]
+pub struct SearchInfo {
+    pub depth: u32,
+    pub score: i32,
+    pub nodes: u64,
+    pub elapsed: Duration,
+    /// Best line found, starting with the move to play
+    pub pv: Vec<Move>,
+}
+
+impl SearchInfo {
+    pub fn best_move(&self) -> Option<Move> {
+        self.pv.first().copied()
+    }
+}
+
+struct Searcher<'a> {
+    table: &'a mut TranspositionTable,
+    nodes: u64,
+    deadline: Option<Instant>,
+    stop: Option<Arc<AtomicBool>>,
+    aborted: bool,
+    /// Off until the first iteration is done
+    can_stop: bool,
+    previous_pv: Vec<Move>,
+}
+
+impl Searcher<'_> {
+    fn should_stop(&mut self) -> bool {
+        if self.can_stop && !self.aborted && self.nodes.is_multiple_of(1024) {
+            self.aborted = self
+                .deadline
+                .is_some_and(|deadline| Instant::now() >= deadline)
+                || self
+                    .stop
+                    .as_ref()
+                    .is_some_and(|stop| stop.load(Ordering::Relaxed));
+        }
+        self.aborted
+    }
+
+    /// Orders moves so the likely best ones are searched first
+    fn order_moves(
+        &self,
+        position: &Position,
+        moves: &mut [Move],
+        ply: usize,
+        table_move: Option<Move>,
+    ) {
+        let pv_move = self.previous_pv.get(ply).copied();
+        moves.sort_by_cached_key(|&mv| {
+            if Some(mv) == pv_move {
+                return i32::MIN;
+            }
+            if Some(mv) == table_move {
+                return i32::MIN + 1;
+            }
+
+            // Most valuable victim, least valuable attacker
+            let victim = position
+                .capture_square(mv)
+                .and_then(|square| position.piece_at(square))
+                .map_or(0, |(_, piece_type)| piece_value(piece_type).max(100));
+            let attacker = position
+                .piece_at(mv.from)
+                .map_or(0, |(_, piece_type)| piece_value(piece_type));
+            let promotion = mv.promotion.map_or(0, piece_value);
+
+            if victim > 0 || promotion > 0 {
+                -(victim * 10 - attacker / 10 + promotion)
+            } else {
+                0
+            }
+        });
+    }
+
+    fn negamax(
+        &mut self,
+        position: &mut Position,
+        depth: u32,
+        ply: usize,
+        mut alpha: i32,
+        beta: i32,
+        pv: &mut Vec<Move>,
+    ) -> i32 {
+        self.nodes += 1;
+        pv.clear();
+
+        if ply > 0
+            && (position.halfmove_clock() >= 100
+                || position.repetition_count() >= 2
+                || position.is_insufficient_material())
+        {
+            return 0;
+        }
+
+        let mut moves = position.legal_moves();
+        if moves.is_empty() {
+            return if position.is_in_check(position.side_to_move()) {
+                -MATE_SCORE + ply as i32
+            } else {
+                0
+            };
+        }
+        if depth == 0 {
+            return self.quiescence(position, alpha, beta);
+        }
+
+        // Reuse what an earlier search found out about this position
+        let hash = position.hash();
+        let entry = self.table.probe(hash);
+        if let Some(entry) = entry.filter(|entry| ply > 0 && entry.depth >= depth) {
+            let score = score_from_table(entry.score, ply);
+            let usable = match entry.bound {
+                Bound::Exact => true,
+                Bound::Lower => score >= beta,
+                Bound::Upper => score <= alpha,
+            };
+            if usable {
+                pv.extend(entry.best_move);
+                return score;
+            }
+        }
+
+        let original_alpha = alpha;
+        self.order_moves(
+            position,
+            &mut moves,
+            ply,
+            entry.and_then(|entry| entry.best_move),
+        );
+        let mut child_pv = Vec::new();
+        for mv in moves {
+            position.make_move(mv);
+            let score = -self.negamax(position, depth - 1, ply + 1, -beta, -alpha, &mut child_pv);
+            position.unmake_move();
+
+            if self.should_stop() {
+                return 0;
+            }
+            if score > alpha {
+                alpha = score;
+                pv.clear();
+                pv.push(mv);
+                pv.extend_from_slice(&child_pv);
+                if alpha >= beta {
+                    break;
+                }
+            }
+        }
+
+        let bound = if alpha >= beta {
+            Bound::Lower
+        } else if alpha > original_alpha {
+            Bound::Exact
+        } else {
+            Bound::Upper
+        };
+        self.table.store(Entry {
+            hash,
+            depth,
+            score: score_to_table(alpha, ply),
+            bound,
+            best_move: pv.first().copied(),
+        });
+
+        alpha
+    }
+
+    /// Keeps searching captures so the evaluation isn't taken in the middle of an exchange
+    fn quiescence(&mut self, position: &mut Position, mut alpha: i32, beta: i32) -> i32 {
+        self.nodes += 1;
+
+        let stand_pat = evaluate(position);
+        if stand_pat >= beta {
+            return stand_pat;
+        }
+        alpha = alpha.max(stand_pat);
+
+        let mut captures: Vec<Move> = position
+            .legal_moves()
+            .into_iter()
+            .filter(|&mv| position.capture_square(mv).is_some() || mv.promotion.is_some())
+            .collect();
+        self.order_moves(position, &mut captures, usize::MAX, None);
+
+        for mv in captures {
+            position.make_move(mv);
+            let score = -self.quiescence(position, -beta, -alpha);
+            position.unmake_move();
+
+            if self.should_stop() {
+                return 0;
+            }
+            if score > alpha {
+                alpha = score;
+                if alpha >= beta {
+                    break;
+                }
+            }
+        }
+
+        alpha
+    }
+}
+
+/// Mate scores are stored relative to the position they're found in rather than the root,
+/// since the same position can come up at different plies
+fn score_to_table(score: i32, ply: usize) -> i32 {
+    if is_mate_score(score) {
+        score + score.signum() * ply as i32
+    } else {
+        score
+    }
+}
+
+fn score_from_table(score: i32, ply: usize) -> i32 {
+    if is_mate_score(score) {
+        score - score.signum() * ply as i32
+    } else {
+        score
+    }
+}
+
+/// Searches for the best move with iterative deepening, calling `report` after every
+/// finished depth. Returns the deepest finished iteration, or None if there are no legal moves.
+///
+/// Results are kept in `table`, so searching later positions of the same game with it
+/// goes faster.
+pub fn search(
+    position: &Position,
+    limits: &SearchLimits,
+    table: &mut TranspositionTable,
+    mut report: impl FnMut(&SearchInfo),
+) -> Option<SearchInfo> {
+    if position.legal_moves().is_empty() {
+        return None;
+    }
+
+    let start = Instant::now();
+    let mut searcher = Searcher {
+        table,
+        nodes: 0,
+        deadline: limits.time.map(|time| start + time),
+        stop: limits.stop.clone(),
+        aborted: false,
+        can_stop: false,
+        previous_pv: Vec::new(),
+    };
+    let mut position = position.clone();
+    let mut best: Option<SearchInfo> = None;
+
+    for depth in 1..=limits.depth.unwrap_or(MAX_DEPTH).clamp(1, MAX_DEPTH) {
+        let mut pv = Vec::new();
+        let score = searcher.negamax(
+            &mut position,
+            depth,
+            0,
+            -MATE_SCORE - 1,
+            MATE_SCORE + 1,
+            &mut pv,
+        );
+
+        if searcher.aborted {
+            break;
+        }
+
+        let info = SearchInfo {
+            depth,
+            score,
+            nodes: searcher.nodes,
+            elapsed: start.elapsed(),
+            pv: pv.clone(),
+        };
+        report(&info);
+        best = Some(info);
+        searcher.previous_pv = pv;
+        searcher.can_stop = true;
+
+        // No point looking deeper once a forced mate has been found
+        if is_mate_score(score) {
+            break;
+        }
+    }
+
+    best
+}
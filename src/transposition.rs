@@ -0,0 +1,84 @@
+use crate::position::Move;
+
+/// How the stored score relates to the position's real score
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Bound {
+    Exact,
+    /// The real score is at least this, because the search was cut off
+    Lower,
+    /// The real score is at most this, because no move beat alpha
+    Upper,
+}
+
+/// What a search found out about one position
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Entry {
+    pub hash: u64,
+    pub depth: u32,
+    pub score: i32,
+    pub bound: Bound,
+    pub best_move: Option<Move>,
+}
+
+/// Fixed-size cache of search results, indexed by Zobrist hash.
+/// When two positions share a slot, the one searched deeper is kept.
+pub struct TranspositionTable {
+    entries: Vec<Option<Entry>>,
+}
+
+impl Default for TranspositionTable {
+    fn default() -> Self {
+        Self::new(16)
+    }
+}
+
+impl TranspositionTable {
+    /// A table using about `megabytes` of memory
+    pub fn new(megabytes: usize) -> Self {
+        let wanted = megabytes * 1024 * 1024 / std::mem::size_of::<Option<Entry>>();
+        // A power of two, so the slot is just the hash's low bits
+        let size = if wanted.is_power_of_two() {
+            wanted
+        } else {
+            (wanted.next_power_of_two() / 2).max(1)
+        };
+        Self {
+            entries: vec![None; size],
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    fn slot(&self, hash: u64) -> usize {
+        (hash & (self.entries.len() as u64 - 1)) as usize
+    }
+
+    /// Returns what's stored for the position with this hash, if anything
+    pub fn probe(&self, hash: u64) -> Option<Entry> {
+        self.entries[self.slot(hash)].filter(|entry| entry.hash == hash)
+    }
+
+    /// Stores an entry unless its slot holds a different position searched deeper
+    pub fn store(&mut self, entry: Entry) {
+        let slot = self.slot(entry.hash);
+        let replace = match self.entries[slot] {
+            None => true,
+            Some(old) => old.hash == entry.hash || entry.depth >= old.depth,
+        };
+        if replace {
+            self.entries[slot] = Some(entry);
+        }
+    }
+
+    pub fn clear(&mut self) {
+        for entry in &mut self.entries {
+            *entry = None;
+        }
+    }
+}
@@ -0,0 +1,264 @@
+use std::{
+    io::{self, BufRead, BufReader, Write},
+    process::{Child, ChildStdin, Command, Stdio},
+    sync::mpsc::{self, Receiver, RecvTimeoutError, TryRecvError},
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    fen::piece_to_char,
+    position::*,
+    search::{is_mate_score, MATE_SCORE},
+};
+
+/// Writes a move in the long algebraic notation UCI uses, like `e2e4` or `e7e8q`
+pub fn move_to_uci(mv: Move) -> String {
+    let mut uci = square_name(mv.from) + &square_name(mv.to);
+    if let Some(promotion) = mv.promotion {
+        uci.push(piece_to_char(PieceColor::Black, promotion));
+    }
+    uci
+}
+
+/// Writes a search score as UCI's `cp <centipawns>` or `mate <moves>`
+pub fn score_to_uci(score: i32) -> String {
+    if is_mate_score(score) {
+        // Mates are counted in moves rather than plies, negative when getting mated
+        let plies = MATE_SCORE - score.abs();
+        let moves = (plies + 1) / 2;
+        format!("mate {}", if score > 0 { moves } else { -moves })
+    } else {
+        format!("cp {}", score)
+    }
+}
+
+impl Position {
+    /// Finds the legal move written in UCI's long algebraic notation
+    pub fn parse_uci_move(&self, uci: &str) -> Option<Move> {
+        if !uci.is_ascii() || (uci.len() != 4 && uci.len() != 5) {
+            return None;
+        }
+        let from = parse_square(&uci[..2])?;
+        let to = parse_square(&uci[2..4])?;
+        let promotion = match uci[4..].chars().next() {
+            Some('q') => Some(PieceType::Queen),
+            Some('r') => Some(PieceType::Rook),
+            Some('b') => Some(PieceType::Bishop),
+            Some('n') => Some(PieceType::Knight),
+            Some(_) => return None,
+            None => None,
+        };
+
+        self.legal_moves()
+            .into_iter()
+            .find(|mv| mv.from == from && mv.to == to && mv.promotion == promotion)
+    }
+
+    /// Sets up the position from the arguments of a UCI `position` command, like
+    /// `startpos moves e2e4 e7e5` or `fen <FEN> moves ...`
+    pub fn from_uci_position(args: &str) -> Option<Self> {
+        let mut words = args.split_whitespace().peekable();
+        let mut position = match words.next()? {
+            "startpos" => Self::default(),
+            "fen" => {
+                let mut fen = Vec::new();
+                while let Some(&word) = words.peek() {
+                    if word == "moves" {
+                        break;
+                    }
+                    fen.push(word);
+                    words.next();
+                }
+                Self::from_fen(&fen.join(" ")).ok()?
+            }
+            _ => return None,
+        };
+
+        if let Some(word) = words.next() {
+            if word != "moves" {
+                return None;
+            }
+            for uci in words {
+                let mv = position.parse_uci_move(uci)?;
+                position.make_move(mv);
+            }
+        }
+        Some(position)
+    }
+}
+
+/// A UCI engine running as a child process
+pub struct UciEngine {
+    child: Child,
+    stdin: ChildStdin,
+    lines: Receiver<String>,
+    /// Position the engine is searching, to read its `bestmove` against
+    searching: Option<Position>,
+    /// Searches that were stopped but haven't sent their `bestmove` yet
+    stale_searches: usize,
+}
+
+impl UciEngine {
+    /// Starts the engine from a command line like `stockfish` or `./engine --flag`, and waits
+    /// up to `timeout` for it to finish the UCI handshake
+    pub fn spawn(command: &str, timeout: Duration) -> io::Result<Self> {
+        let mut words = command.split_whitespace();
+        let program = words
+            .next()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "empty engine command"))?;
+        let mut child = Command::new(program)
+            .args(words)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()?;
+        let stdin = child.stdin.take().expect("engine stdin is piped");
+        let stdout = child.stdout.take().expect("engine stdout is piped");
+
+        // Read on another thread, so the game can check for replies without blocking
+        let (sender, lines) = mpsc::channel();
+        thread::spawn(move || {
+            for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+                if sender.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+
+        let mut engine = Self {
+            child,
+            stdin,
+            lines,
+            searching: None,
+            stale_searches: 0,
+        };
+        engine.send("uci")?;
+        engine.wait_for("uciok", timeout)?;
+        engine.send("isready")?;
+        engine.wait_for("readyok", timeout)?;
+        Ok(engine)
+    }
+
+    fn send(&mut self, command: &str) -> io::Result<()> {
+        writeln!(self.stdin, "{}", command)?;
+        self.stdin.flush()
+    }
+
+    /// Returns the next line the engine wrote, or None if there's none within `timeout`
+    fn next_line(&mut self, timeout: Option<Duration>) -> io::Result<Option<String>> {
+        let line = match timeout {
+            Some(timeout) => self.lines.recv_timeout(timeout).map_err(|err| match err {
+                RecvTimeoutError::Timeout => TryRecvError::Empty,
+                RecvTimeoutError::Disconnected => TryRecvError::Disconnected,
+            }),
+            None => self.lines.try_recv(),
+        };
+        match line {
+            Ok(line) => Ok(Some(line)),
+            Err(TryRecvError::Empty) => Ok(None),
+            Err(TryRecvError::Disconnected) => Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "engine closed its output",
+            )),
+        }
+    }
+
+    fn wait_for(&mut self, reply: &str, timeout: Duration) -> io::Result<()> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            let left = deadline.saturating_duration_since(Instant::now());
+            match self.next_line(Some(left))? {
+                Some(line) if line.trim() == reply => return Ok(()),
+                Some(_) => {}
+                None => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::TimedOut,
+                        format!("engine didn't answer with {}", reply),
+                    ))
+                }
+            }
+        }
+    }
+
+    /// Asks the engine for a move after `moves` have been played from `start`.
+    /// Any search still running is stopped first.
+    pub fn start_search(
+        &mut self,
+        start: &Position,
+        moves: &[Move],
+        think_time: Duration,
+    ) -> io::Result<()> {
+        self.stop()?;
+
+        // Sending the moves rather than just the FEN lets the engine see repetitions
+        let mut position = start.clone();
+        let mut command = format!("position fen {}", start.to_fen());
+        if !moves.is_empty() {
+            command.push_str(" moves");
+        }
+        for &mv in moves {
+            command.push(' ');
+            command.push_str(&move_to_uci(mv));
+            position.make_move(mv);
+        }
+
+        self.send(&command)?;
+        self.send(&format!("go movetime {}", think_time.as_millis()))?;
+        self.searching = Some(position);
+        Ok(())
+    }
+
+    /// Stops the current search, if any. Its move is thrown away.
+    pub fn stop(&mut self) -> io::Result<()> {
+        if self.searching.take().is_some() {
+            self.stale_searches += 1;
+            self.send("stop")?;
+        }
+        Ok(())
+    }
+
+    /// Returns the searched move once the engine has sent it, without blocking
+    pub fn poll_best_move(&mut self) -> io::Result<Option<Move>> {
+        self.read_best_move(None)
+    }
+
+    /// Waits up to `timeout` for the searched move
+    pub fn wait_best_move(&mut self, timeout: Duration) -> io::Result<Option<Move>> {
+        self.read_best_move(Some(timeout))
+    }
+
+    fn read_best_move(&mut self, timeout: Option<Duration>) -> io::Result<Option<Move>> {
+        while let Some(line) = self.next_line(timeout)? {
+            let mut words = line.split_whitespace();
+            if words.next() != Some("bestmove") {
+                continue;
+            }
+            if self.stale_searches > 0 {
+                self.stale_searches -= 1;
+                continue;
+            }
+
+            let position = match self.searching.take() {
+                Some(position) => position,
+                None => continue,
+            };
+            let uci = words.next().unwrap_or("");
+            return match position.parse_uci_move(uci) {
+                Some(mv) => Ok(Some(mv)),
+                None => Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("engine sent illegal move '{}'", uci),
+                )),
+            };
+        }
+        Ok(None)
+    }
+}
+
+impl Drop for UciEngine {
+    fn drop(&mut self) {
+        let _ = self.send("quit");
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
@@ -1,5 +1,10 @@
 use crate::board::*;
-use bevy::prelude::*;
+use bevy::{input::mouse::MouseWheel, prelude::*, render::camera::Camera};
+use bevy_chess::{
+    clock::format_clock,
+    pgn::GameRecord,
+    position::{Move, PieceColor, PieceType, Position, PROMOTION_TYPES},
+};
 
 // Component to mark the Text entity
 struct NextMoveText;
@@ -47,20 +52,642 @@ fn init_next_move_text(
         });
 }
 
-/// Update text with the correct turn
+/// Update text with the correct turn, or the result once the game is over
 fn next_move_text_update(
-    turn: ChangedRes<PlayerTurn>,
+    turn: Res<PlayerTurn>,
+    status: Res<GameStatus>,
     mut query: Query<(&mut Text, &NextMoveText)>,
 ) {
+    let value = if let Some(game_over) = status.game_over {
+        game_over.to_string()
+    } else {
+        format!("Next move: {}", turn.0)
+    };
+
+    for (mut text, _tag) in query.iter_mut() {
+        // Only touch the text when it changes, so it isn't marked as mutated every frame
+        if text.value != value {
+            text.value = value.clone();
+        }
+    }
+}
+
+// Component to mark the clock Text entity
+struct ClockText;
+
+/// Initialize the clock text, under the next move text
+fn init_clock_text(
+    commands: &mut Commands,
+    asset_server: ResMut<AssetServer>,
+    mut color_materials: ResMut<Assets<ColorMaterial>>,
+) {
+    spawn_hud_text(
+        commands,
+        &asset_server,
+        &mut color_materials,
+        55.,
+        ClockText,
+    );
+}
+
+/// Spawn an empty line of text at the top left, `top` pixels down, tagged with `marker`
+fn spawn_hud_text(
+    commands: &mut Commands,
+    asset_server: &AssetServer,
+    color_materials: &mut Assets<ColorMaterial>,
+    top: f32,
+    marker: impl Send + Sync + 'static,
+) {
+    let font = asset_server.load("fonts/FiraSans-Bold.ttf");
+    let material = color_materials.add(Color::NONE.into());
+
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    left: Val::Px(10.),
+                    top: Val::Px(top),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            material,
+            ..Default::default()
+        })
+        .with_children(|parent| {
+            parent
+                .spawn(TextBundle {
+                    text: Text {
+                        value: String::new(),
+                        font,
+                        style: TextStyle {
+                            font_size: 30.0,
+                            color: Color::rgb(0.8, 0.8, 0.8),
+                            ..Default::default()
+                        },
+                    },
+                    ..Default::default()
+                })
+                .with(marker);
+        });
+}
+
+/// Update the time left on both clocks
+fn clock_text_update(game_clock: Res<GameClock>, mut query: Query<(&mut Text, &ClockText)>) {
+    let value = match &game_clock.clock {
+        Some(clock) => format!(
+            "White {}   Black {}",
+            format_clock(clock.remaining(PieceColor::White)),
+            format_clock(clock.remaining(PieceColor::Black))
+        ),
+        None => String::new(),
+    };
+
+    for (mut text, _tag) in query.iter_mut() {
+        if text.value != value {
+            text.value = value.clone();
+        }
+    }
+}
+
+// Component to mark the material difference Text entity
+struct MaterialText;
+
+/// Initialize the material difference text, under the clocks
+fn init_material_text(
+    commands: &mut Commands,
+    asset_server: ResMut<AssetServer>,
+    mut color_materials: ResMut<Assets<ColorMaterial>>,
+) {
+    spawn_hud_text(
+        commands,
+        &asset_server,
+        &mut color_materials,
+        90.,
+        MaterialText,
+    );
+}
+
+/// Update which side is ahead on material, and by how much
+fn material_text_update(position: Res<Position>, mut query: Query<(&mut Text, &MaterialText)>) {
+    let white = position.material(PieceColor::White);
+    let black = position.material(PieceColor::Black);
+    let value = if white > black {
+        format!("White +{}", white - black)
+    } else if black > white {
+        format!("Black +{}", black - white)
+    } else {
+        String::new()
+    };
+
     for (mut text, _tag) in query.iter_mut() {
-        text.value = format!("Next move: {}", turn.0);
+        if text.value != value {
+            text.value = value.clone();
+        }
+    }
+}
+
+// Component for the file and rank labels around the board
+#[derive(Clone, Copy)]
+enum CoordinateLabel {
+    File(u8),
+    Rank(u8),
+}
+
+/// Initialize a label for every file and rank
+fn init_coordinate_labels(commands: &mut Commands, asset_server: ResMut<AssetServer>) {
+    let font = asset_server.load("fonts/FiraSans-Bold.ttf");
+
+    for i in 0..8 {
+        for &label in [CoordinateLabel::File(i), CoordinateLabel::Rank(i)].iter() {
+            let value = match label {
+                CoordinateLabel::File(y) => ((b'a' + y) as char).to_string(),
+                CoordinateLabel::Rank(x) => (x + 1).to_string(),
+            };
+
+            commands
+                .spawn(TextBundle {
+                    style: Style {
+                        position_type: PositionType::Absolute,
+                        ..Default::default()
+                    },
+                    text: Text {
+                        value,
+                        font: font.clone(),
+                        style: TextStyle {
+                            font_size: 20.0,
+                            color: Color::rgb(0.8, 0.8, 0.8),
+                            ..Default::default()
+                        },
+                    },
+                    ..Default::default()
+                })
+                .with(label);
+        }
+    }
+}
+
+/// Keep the labels beside the files and ranks on the screen, along the board's edges
+/// nearest the camera and on its left
+fn coordinate_labels_update(
+    windows: Res<Windows>,
+    view: Res<BoardView>,
+    camera_query: Query<(&Camera, &GlobalTransform), With<BoardCamera>>,
+    mut query: Query<(&CoordinateLabel, &mut Style)>,
+) {
+    let (camera, camera_transform) = match camera_query.iter().next() {
+        Some(camera) => camera,
+        None => return,
+    };
+
+    // Seen from Black's side, those edges are behind the 8th rank and the h-file
+    let edge = if view.flipped { 7.7 } else { -0.7 };
+    for (label, mut style) in query.iter_mut() {
+        let world_position = match *label {
+            CoordinateLabel::File(y) => Vec3::new(edge, 0., y as f32),
+            CoordinateLabel::Rank(x) => Vec3::new(x as f32, 0., edge),
+        };
+
+        if let Some(screen_position) =
+            camera.world_to_screen(&windows, camera_transform, world_position)
+        {
+            // Roughly center the text on the point
+            style.position = Rect {
+                left: Val::Px(screen_position.x - 5.),
+                bottom: Val::Px(screen_position.y - 10.),
+                ..Default::default()
+            };
+        }
+    }
+}
+
+/// Spawn a button with a text label, tagged with `marker`
+pub fn spawn_button(
+    parent: &mut ChildBuilder,
+    label: String,
+    font: Handle<Font>,
+    material: Handle<ColorMaterial>,
+    marker: impl Send + Sync + 'static,
+) {
+    parent
+        .spawn(ButtonBundle {
+            style: Style {
+                // Longer labels widen the button
+                min_size: Size::new(Val::Px(120.), Val::Px(40.)),
+                padding: Rect {
+                    left: Val::Px(8.),
+                    right: Val::Px(8.),
+                    ..Default::default()
+                },
+                margin: Rect::all(Val::Px(4.)),
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..Default::default()
+            },
+            material,
+            ..Default::default()
+        })
+        .with(marker)
+        .with_children(|parent| {
+            parent.spawn(TextBundle {
+                text: Text {
+                    value: label,
+                    font,
+                    style: TextStyle {
+                        font_size: 24.0,
+                        color: Color::rgb(0.8, 0.8, 0.8),
+                        ..Default::default()
+                    },
+                },
+                ..Default::default()
+            });
+        });
+}
+
+// Component to mark the promotion picker's root node
+struct PromotionPicker;
+
+// Component holding the piece a promotion button picks
+struct PromotionButton(PieceType);
+
+/// Show the promotion picker while a promotion is pending, and remove it afterwards
+fn promotion_picker(
+    commands: &mut Commands,
+    pending_promotion: ChangedRes<PendingPromotion>,
+    asset_server: Res<AssetServer>,
+    mut color_materials: ResMut<Assets<ColorMaterial>>,
+    picker_query: Query<Entity, With<PromotionPicker>>,
+) {
+    for entity in picker_query.iter() {
+        commands.despawn_recursive(entity);
+    }
+
+    if pending_promotion.mv.is_none() {
+        return;
+    }
+
+    let font = asset_server.load("fonts/FiraSans-Bold.ttf");
+    let material = color_materials.add(Color::NONE.into());
+    let button_material = color_materials.add(Color::rgb(0.15, 0.15, 0.15).into());
+
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    right: Val::Px(10.),
+                    top: Val::Px(10.),
+                    ..Default::default()
+                },
+                flex_direction: FlexDirection::ColumnReverse,
+                ..Default::default()
+            },
+            material,
+            ..Default::default()
+        })
+        .with(PromotionPicker)
+        .with_children(|parent| {
+            for &piece_type in PROMOTION_TYPES.iter() {
+                spawn_button(
+                    parent,
+                    piece_type.to_string(),
+                    font.clone(),
+                    button_material.clone(),
+                    PromotionButton(piece_type),
+                );
+            }
+        });
+}
+
+/// Play the pending promotion with the piece whose button was clicked
+fn promotion_buttons(
+    mut pending_promotion: ResMut<PendingPromotion>,
+    mut make_move_events: ResMut<Events<MakeMoveEvent>>,
+    query: Query<(&Interaction, &PromotionButton), Mutated<Interaction>>,
+) {
+    for (interaction, button) in query.iter() {
+        if *interaction != Interaction::Clicked {
+            continue;
+        }
+
+        if let Some(mv) = pending_promotion.mv.take() {
+            make_move_events.send(MakeMoveEvent(Move {
+                promotion: Some(button.0),
+                ..mv
+            }));
+        }
+    }
+}
+
+// Component for the buttons that take back and replay moves
+enum HistoryButton {
+    Undo,
+    Redo,
+}
+
+/// Initialize the undo and redo buttons
+fn init_history_buttons(
+    commands: &mut Commands,
+    asset_server: ResMut<AssetServer>,
+    mut color_materials: ResMut<Assets<ColorMaterial>>,
+) {
+    let font = asset_server.load("fonts/FiraSans-Bold.ttf");
+    let material = color_materials.add(Color::NONE.into());
+    let button_material = color_materials.add(Color::rgb(0.15, 0.15, 0.15).into());
+
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    left: Val::Px(10.),
+                    bottom: Val::Px(10.),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            material,
+            ..Default::default()
+        })
+        .with_children(|parent| {
+            spawn_button(
+                parent,
+                "Undo".to_string(),
+                font.clone(),
+                button_material.clone(),
+                HistoryButton::Undo,
+            );
+            spawn_button(
+                parent,
+                "Redo".to_string(),
+                font,
+                button_material,
+                HistoryButton::Redo,
+            );
+        });
+}
+
+fn history_buttons(
+    mut unmake_move_events: ResMut<Events<UnmakeMoveEvent>>,
+    mut redo_move_events: ResMut<Events<RedoMoveEvent>>,
+    query: Query<(&Interaction, &HistoryButton), Mutated<Interaction>>,
+) {
+    for (interaction, button) in query.iter() {
+        if *interaction != Interaction::Clicked {
+            continue;
+        }
+
+        match button {
+            HistoryButton::Undo => unmake_move_events.send(UnmakeMoveEvent),
+            HistoryButton::Redo => redo_move_events.send(RedoMoveEvent),
+        }
+    }
+}
+
+/// Rows of moves the move list shows at once
+const MOVE_LIST_ROWS: usize = 10;
+
+// Component to mark the move list's root node
+struct MoveList;
+
+// Component for the buttons in the move list
+enum MoveListButton {
+    /// Shows the position after this many moves
+    Ply(usize),
+    /// Goes back to the live game
+    Live,
+}
+
+/// What the move list shows, so it's only rebuilt when that changes
+#[derive(PartialEq)]
+struct MoveListContents {
+    line: Vec<Move>,
+    live_ply: usize,
+    viewed_ply: Option<usize>,
+    first_row: usize,
+}
+
+#[derive(Default)]
+struct MoveListState {
+    contents: Option<MoveListContents>,
+    first_row: usize,
+    wheel_reader: EventReader<MouseWheel>,
+}
+
+/// Show the game's moves in a panel beside the board, scrolled with the mouse wheel
+fn move_list(
+    commands: &mut Commands,
+    mut state: Local<MoveListState>,
+    wheel_events: Res<Events<MouseWheel>>,
+    asset_server: Res<AssetServer>,
+    mut color_materials: ResMut<Assets<ColorMaterial>>,
+    record: Res<GameRecord>,
+    upcoming_moves: Res<UpcomingMoves>,
+    history_view: Res<HistoryView>,
+    list_query: Query<Entity, With<MoveList>>,
+) {
+    let line = game_line(&record, &upcoming_moves);
+    let live_ply = record.moves.len();
+    let current_ply = history_view.ply.unwrap_or(live_ply);
+    // When Black moves first, the first row's White cell stays empty
+    let offset = (record.start.side_to_move() == PieceColor::Black) as usize;
+    let rows = (line.len() + offset + 1) / 2;
+
+    let mut first_row = state.first_row;
+    for wheel in state.wheel_reader.iter(&wheel_events) {
+        if wheel.y > 0. {
+            first_row = first_row.saturating_sub(1);
+        } else if wheel.y < 0. {
+            first_row += 1;
+        }
+    }
+
+    // Scroll to the current move whenever it changes
+    let current_changed = state
+        .contents
+        .as_ref()
+        .is_none_or(|contents| contents.viewed_ply.unwrap_or(contents.live_ply) != current_ply);
+    if current_changed && current_ply > 0 {
+        let row = (current_ply - 1 + offset) / 2;
+        if row < first_row {
+            first_row = row;
+        } else if row >= first_row + MOVE_LIST_ROWS {
+            first_row = row + 1 - MOVE_LIST_ROWS;
+        }
+    }
+    first_row = first_row.min(rows.saturating_sub(MOVE_LIST_ROWS));
+    state.first_row = first_row;
+
+    let contents = MoveListContents {
+        line,
+        live_ply,
+        viewed_ply: history_view.ply,
+        first_row,
+    };
+    if state.contents.as_ref() == Some(&contents) {
+        return;
+    }
+
+    for entity in list_query.iter() {
+        commands.despawn_recursive(entity);
+    }
+
+    if !contents.line.is_empty() {
+        let sans = move_sans(&record, &contents.line);
+        let font = asset_server.load("fonts/FiraSans-Bold.ttf");
+        let material = color_materials.add(Color::rgba(0., 0., 0., 0.5).into());
+        let cell_material = color_materials.add(Color::NONE.into());
+        let current_material = color_materials.add(Color::rgb(0.3, 0.3, 0.6).into());
+        let button_material = color_materials.add(Color::rgb(0.15, 0.15, 0.15).into());
+
+        commands
+            .spawn(NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    position: Rect {
+                        right: Val::Px(10.),
+                        bottom: Val::Px(10.),
+                        ..Default::default()
+                    },
+                    flex_direction: FlexDirection::ColumnReverse,
+                    padding: Rect::all(Val::Px(4.)),
+                    ..Default::default()
+                },
+                material,
+                ..Default::default()
+            })
+            .with(MoveList)
+            .with_children(|parent| {
+                for row in first_row..rows.min(first_row + MOVE_LIST_ROWS) {
+                    parent
+                        .spawn(NodeBundle {
+                            material: cell_material.clone(),
+                            ..Default::default()
+                        })
+                        .with_children(|parent| {
+                            let move_number = record.start.fullmove_number() as usize + row;
+                            spawn_move_list_cell(
+                                parent,
+                                format!("{}.", move_number),
+                                font.clone(),
+                                cell_material.clone(),
+                                None,
+                            );
+
+                            for column in 0..2 {
+                                // Index into the line of the move in this cell
+                                let index = (row * 2 + column)
+                                    .checked_sub(offset)
+                                    .filter(|&index| index < sans.len());
+                                let (label, material, button) = match index {
+                                    Some(index) => (
+                                        sans[index].clone(),
+                                        if index + 1 == current_ply {
+                                            current_material.clone()
+                                        } else {
+                                            cell_material.clone()
+                                        },
+                                        Some(MoveListButton::Ply(index + 1)),
+                                    ),
+                                    None => (String::new(), cell_material.clone(), None),
+                                };
+                                spawn_move_list_cell(parent, label, font.clone(), material, button);
+                            }
+                        });
+                }
+
+                if contents.viewed_ply.is_some() {
+                    spawn_button(
+                        parent,
+                        "Back to live".to_string(),
+                        font,
+                        button_material,
+                        MoveListButton::Live,
+                    );
+                }
+            });
     }
+
+    state.contents = Some(contents);
+}
+
+/// The moves of `line` in Standard Algebraic Notation
+fn move_sans(record: &GameRecord, line: &[Move]) -> Vec<String> {
+    let mut position = record.start.clone();
+    line.iter()
+        .map(|&mv| {
+            let san = position.to_san(mv);
+            position.make_move(mv);
+            san
+        })
+        .collect()
 }
 
-/// Demo system to show off Query transformers
-fn log_text_changes(query: Query<&Text, Mutated<Text>>) {
-    for text in query.iter() {
-        println!("New text: {}", text.value);
+/// Spawn one cell of the move list, which is a button if it's given one
+fn spawn_move_list_cell(
+    parent: &mut ChildBuilder,
+    label: String,
+    font: Handle<Font>,
+    material: Handle<ColorMaterial>,
+    button: Option<MoveListButton>,
+) {
+    let style = Style {
+        size: Size::new(Val::Px(70.), Val::Px(26.)),
+        padding: Rect {
+            left: Val::Px(4.),
+            ..Default::default()
+        },
+        align_items: AlignItems::Center,
+        ..Default::default()
+    };
+
+    match button {
+        Some(button) => parent
+            .spawn(ButtonBundle {
+                style,
+                material,
+                ..Default::default()
+            })
+            .with(button),
+        None => parent.spawn(NodeBundle {
+            style,
+            material,
+            ..Default::default()
+        }),
+    }
+    .with_children(|parent| {
+        parent.spawn(TextBundle {
+            text: Text {
+                value: label,
+                font,
+                style: TextStyle {
+                    font_size: 20.0,
+                    color: Color::rgb(0.8, 0.8, 0.8),
+                    ..Default::default()
+                },
+            },
+            ..Default::default()
+        });
+    });
+}
+
+/// Look at the position after the clicked move, or go back to the live game
+fn move_list_buttons(
+    mut history_view: ResMut<HistoryView>,
+    record: Res<GameRecord>,
+    query: Query<(&Interaction, &MoveListButton), Mutated<Interaction>>,
+) {
+    for (interaction, button) in query.iter() {
+        if *interaction != Interaction::Clicked {
+            continue;
+        }
+
+        history_view.ply = match *button {
+            // The position after the last played move is the live one
+            MoveListButton::Ply(ply) if ply != record.moves.len() => Some(ply),
+            _ => None,
+        };
     }
 }
 
@@ -68,7 +695,18 @@ pub struct UiPlugin;
 impl Plugin for UiPlugin {
     fn build(&self, app: &mut AppBuilder) {
         app.add_startup_system(init_next_move_text.system())
+            .add_startup_system(init_clock_text.system())
+            .add_startup_system(init_material_text.system())
+            .add_startup_system(init_coordinate_labels.system())
+            .add_startup_system(init_history_buttons.system())
             .add_system(next_move_text_update.system())
-            .add_system(log_text_changes.system());
+            .add_system(clock_text_update.system())
+            .add_system(material_text_update.system())
+            .add_system(coordinate_labels_update.system())
+            .add_system(promotion_picker.system())
+            .add_system(promotion_buttons.system())
+            .add_system(history_buttons.system())
+            .add_system(move_list.system())
+            .add_system(move_list_buttons.system());
     }
 }
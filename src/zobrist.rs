@@ -0,0 +1,57 @@
+use crate::position::{CastlingRights, PieceColor, PieceType};
+
+const PIECE_KEYS: usize = 0;
+const SIDE_KEY: usize = PIECE_KEYS + 2 * 6 * 64;
+const CASTLING_KEYS: usize = SIDE_KEY + 1;
+const EN_PASSANT_KEYS: usize = CASTLING_KEYS + 4;
+const KEY_COUNT: usize = EN_PASSANT_KEYS + 8;
+
+/// Random keys from SplitMix64 with a fixed seed, so hashes are the same in every build
+const fn generate_keys() -> [u64; KEY_COUNT] {
+    let mut keys = [0; KEY_COUNT];
+    let mut state: u64 = 0x2545_f491_4f6c_dd1d;
+    let mut i = 0;
+    while i < KEY_COUNT {
+        state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        keys[i] = z ^ (z >> 31);
+        i += 1;
+    }
+    keys
+}
+
+static KEYS: [u64; KEY_COUNT] = generate_keys();
+
+/// Key for a piece standing on `pos`
+pub fn piece_key(color: PieceColor, piece_type: PieceType, pos: (u8, u8)) -> u64 {
+    KEYS[PIECE_KEYS
+        + color.index() * 6 * 64
+        + piece_type.index() * 64
+        + (pos.0 * 8 + pos.1) as usize]
+}
+
+/// Key added when Black is to move
+pub fn side_key() -> u64 {
+    KEYS[SIDE_KEY]
+}
+
+/// Combined key of every castling right still available
+pub fn castling_key(castling: CastlingRights) -> u64 {
+    [
+        castling.white_king_side,
+        castling.white_queen_side,
+        castling.black_king_side,
+        castling.black_queen_side,
+    ]
+    .iter()
+    .enumerate()
+    .filter(|(_, &allowed)| allowed)
+    .fold(0, |key, (i, _)| key ^ KEYS[CASTLING_KEYS + i])
+}
+
+/// Key for an en passant capture being possible on column `y`
+pub fn en_passant_key(y: u8) -> u64 {
+    KEYS[EN_PASSANT_KEYS + y as usize]
+}
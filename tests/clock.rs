@@ -0,0 +1,137 @@
+use bevy_chess::{
+    clock::{format_clock, Clock, TimeBonus, TimeControl},
+    position::{GameEndReason, GameResult, PieceColor, Position},
+};
+use std::time::Duration;
+
+fn seconds(seconds: u64) -> Duration {
+    Duration::from_secs(seconds)
+}
+
+fn clock(bonus: TimeBonus) -> Clock {
+    let control = TimeControl {
+        base: seconds(60),
+        bonus,
+    };
+    Clock::new(control, PieceColor::White)
+}
+
+#[test]
+fn only_the_side_to_move_runs() {
+    let mut clock = clock(TimeBonus::None);
+    clock.tick(seconds(10));
+    clock.press();
+    clock.tick(seconds(5));
+    assert_eq!(clock.remaining(PieceColor::White), seconds(50));
+    assert_eq!(clock.remaining(PieceColor::Black), seconds(55));
+    assert_eq!(clock.running(), PieceColor::Black);
+}
+
+#[test]
+fn fischer_increment_is_added_after_each_move() {
+    let mut clock = clock(TimeBonus::Increment(seconds(3)));
+    clock.tick(seconds(1));
+    clock.press();
+    assert_eq!(clock.remaining(PieceColor::White), seconds(62));
+}
+
+#[test]
+fn bronstein_delay_gives_back_at_most_the_time_used() {
+    let mut clock = clock(TimeBonus::Delay(seconds(3)));
+    clock.tick(seconds(2));
+    clock.press();
+    assert_eq!(clock.remaining(PieceColor::White), seconds(60));
+
+    clock.tick(seconds(10));
+    clock.press();
+    assert_eq!(clock.remaining(PieceColor::Black), seconds(53));
+}
+
+#[test]
+fn taking_back_a_move_gives_no_bonus() {
+    let mut clock = clock(TimeBonus::Increment(seconds(3)));
+    clock.tick(seconds(4));
+    clock.switch_to(PieceColor::Black);
+    assert_eq!(clock.remaining(PieceColor::White), seconds(56));
+    assert_eq!(clock.running(), PieceColor::Black);
+}
+
+#[test]
+fn flag_falls_at_zero() {
+    let mut clock = clock(TimeBonus::Increment(seconds(3)));
+    clock.tick(seconds(59));
+    assert_eq!(clock.flagged(), None);
+    clock.tick(seconds(2));
+    assert_eq!(clock.flagged(), Some(PieceColor::White));
+    assert_eq!(clock.remaining(PieceColor::White), Duration::default());
+
+    // No increment can save a fallen flag
+    clock.press();
+    assert_eq!(clock.flagged(), Some(PieceColor::White));
+}
+
+#[test]
+fn timeout_loses_unless_the_opponent_cannot_mate() {
+    let timeout = |fen| Position::from_fen(fen).unwrap().timeout(PieceColor::White);
+
+    let win = timeout("4k3/4p3/8/8/8/8/8/4K3 w - - 0 1");
+    assert_eq!(win.result, GameResult::Win(PieceColor::Black));
+    assert_eq!(win.reason, GameEndReason::Timeout);
+
+    // A lone knight can't mate a bare king
+    let draw = timeout("4k3/4n3/8/8/8/8/8/4K3 w - - 0 1");
+    assert_eq!(draw.result, GameResult::Draw);
+    assert_eq!(draw.reason, GameEndReason::TimeoutVsInsufficientMaterial);
+
+    // But it can if the king has its own pieces to get in the way
+    let win = timeout("4k3/4n3/8/8/8/8/P7/4K3 w - - 0 1");
+    assert_eq!(win.result, GameResult::Win(PieceColor::Black));
+
+    // Two knights could mate with the other side's help
+    let win = timeout("4k3/3nn3/8/8/8/8/8/4K3 w - - 0 1");
+    assert_eq!(win.result, GameResult::Win(PieceColor::Black));
+
+    let draw = timeout("4k3/8/8/3b1b2/8/8/8/4K3 w - - 0 1");
+    assert_eq!(draw.result, GameResult::Draw);
+}
+
+#[test]
+fn formats_time_left() {
+    assert_eq!(format_clock(seconds(300)), "5:00");
+    assert_eq!(format_clock(seconds(65)), "1:05");
+    assert_eq!(format_clock(Duration::from_millis(9_340)), "0:09.3");
+    assert_eq!(format_clock(seconds(3_725)), "1:02:05");
+}
+
+#[test]
+fn taking_back_restores_the_time_as_the_move_was_made() {
+    let mut clock = clock(TimeBonus::Increment(seconds(3)));
+    clock.tick(seconds(10));
+    clock.press();
+    clock.tick(seconds(20));
+
+    clock.take_back();
+    assert_eq!(clock.remaining(PieceColor::White), seconds(50));
+    assert_eq!(clock.remaining(PieceColor::Black), seconds(60));
+    assert_eq!(clock.running(), PieceColor::White);
+
+    // The clock keeps running for the side to move again
+    clock.tick(seconds(5));
+    assert_eq!(clock.remaining(PieceColor::White), seconds(45));
+}
+
+#[test]
+fn taking_back_two_moves() {
+    let mut clock = clock(TimeBonus::None);
+    clock.tick(seconds(10));
+    clock.press();
+    clock.tick(seconds(20));
+    clock.press();
+    clock.tick(seconds(5));
+
+    clock.take_back();
+    clock.take_back();
+    assert_eq!(clock.remaining(PieceColor::White), seconds(50));
+    assert_eq!(clock.remaining(PieceColor::Black), seconds(60));
+    assert_eq!(clock.running(), PieceColor::White);
+}
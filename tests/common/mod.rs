@@ -0,0 +1,67 @@
+// Each test file uses only some of these
+#![allow(dead_code)]
+
+use bevy_chess::position::{Move, PieceColor, PieceType, Position};
+
+/// `(x, y)` of a square written like `e4`
+pub fn square(name: &str) -> (u8, u8) {
+    let bytes = name.as_bytes();
+    (bytes[1] - b'1', bytes[0] - b'a')
+}
+
+fn piece_type(letter: char) -> PieceType {
+    match letter.to_ascii_lowercase() {
+        'k' => PieceType::King,
+        'q' => PieceType::Queen,
+        'r' => PieceType::Rook,
+        'b' => PieceType::Bishop,
+        'n' => PieceType::Knight,
+        'p' => PieceType::Pawn,
+        _ => panic!("unknown piece letter {}", letter),
+    }
+}
+
+/// A move written in UCI notation, like `e2e4`, or `e7e8q` for a promotion
+pub fn uci_move(uci: &str) -> Move {
+    let mut mv = Move::new(square(&uci[..2]), square(&uci[2..4]));
+    mv.promotion = uci[4..].chars().next().map(piece_type);
+    mv
+}
+
+/// Writes a move back in UCI notation
+pub fn to_uci(mv: Move) -> String {
+    let name = |(x, y): (u8, u8)| format!("{}{}", (b'a' + y) as char, x + 1);
+    let promotion = match mv.promotion {
+        Some(PieceType::Queen) => "q",
+        Some(PieceType::Rook) => "r",
+        Some(PieceType::Bishop) => "b",
+        Some(PieceType::Knight) => "n",
+        _ => "",
+    };
+    format!("{}{}{}", name(mv.from), name(mv.to), promotion)
+}
+
+/// Plays moves given in UCI notation, checking that each one is legal
+pub fn play(position: &mut Position, moves: &[&str]) {
+    for uci in moves {
+        let mv = uci_move(uci);
+        assert!(position.legal_moves().contains(&mv), "{} is illegal", uci);
+        position.make_move(mv);
+    }
+}
+
+/// A position with only the given pieces, written like `Ke1` for White and `ke8` for Black
+pub fn setup(pieces: &[&str], side_to_move: PieceColor) -> Position {
+    let mut position = Position::empty();
+    for piece in pieces {
+        let letter = piece.chars().next().unwrap();
+        let color = if letter.is_ascii_uppercase() {
+            PieceColor::White
+        } else {
+            PieceColor::Black
+        };
+        position.set_piece(square(&piece[1..]), Some((color, piece_type(letter))));
+    }
+    position.set_side_to_move(side_to_move);
+    position
+}
@@ -0,0 +1,146 @@
+use bevy_chess::position::{GameEndReason, GameOver, GameResult, PieceColor, Position};
+
+mod common;
+use common::{play, setup, square};
+
+fn reason(position: &Position) -> Option<GameEndReason> {
+    position.game_over().map(|game_over| game_over.reason)
+}
+
+const KNIGHTS_OUT_AND_BACK: [&str; 4] = ["g1f3", "g8f6", "f3g1", "f6g8"];
+
+#[test]
+fn threefold_repetition() {
+    let mut position = Position::default();
+    play(&mut position, &KNIGHTS_OUT_AND_BACK);
+    assert_eq!(position.repetition_count(), 2);
+    assert_eq!(position.game_over(), None);
+
+    play(&mut position, &KNIGHTS_OUT_AND_BACK);
+    assert_eq!(position.repetition_count(), 3);
+    assert_eq!(
+        position.game_over(),
+        Some(GameOver {
+            result: GameResult::Draw,
+            reason: GameEndReason::ThreefoldRepetition,
+        })
+    );
+
+    position.unmake_move();
+    assert_eq!(position.game_over(), None);
+}
+
+#[test]
+fn repetitions_need_the_same_side_to_move() {
+    // The white king takes three moves to come back, so now Black is to move instead
+    let mut position = setup(&["ke8", "Ra1", "Ke1"], PieceColor::White);
+    play(&mut position, &["e1d1", "e8d8", "d1d2", "d8e8", "d2e1"]);
+    assert_eq!(position.repetition_count(), 1);
+}
+
+#[test]
+fn capturable_en_passant_makes_a_different_position() {
+    // After e4, the pawn on d4 can take en passant, which it can't once the kings have moved
+    let mut position = setup(&["ke8", "pd4", "Pe2", "Ke1"], PieceColor::White);
+    let kings_out_and_back = ["e8d8", "e1d1", "d8e8", "d1e1"];
+    play(&mut position, &["e2e4"]);
+    assert_eq!(position.en_passant(), Some(square("e3")));
+    play(&mut position, &kings_out_and_back);
+    assert_eq!(position.repetition_count(), 1);
+    play(&mut position, &kings_out_and_back);
+    assert_eq!(position.repetition_count(), 2);
+    play(&mut position, &kings_out_and_back);
+    assert_eq!(position.repetition_count(), 3);
+
+    // Without a pawn to take it, the en passant square makes no difference
+    let mut position = setup(&["ke8", "pa4", "Pe2", "Ke1"], PieceColor::White);
+    play(&mut position, &["e2e4"]);
+    play(&mut position, &kings_out_and_back);
+    assert_eq!(position.repetition_count(), 2);
+}
+
+#[test]
+fn fifty_move_rule() {
+    let mut position = setup(&["ke8", "ph2", "Ra1", "Ke1"], PieceColor::White);
+    position.set_halfmove_clock(98);
+    play(&mut position, &["a1a2"]);
+    assert_eq!(position.game_over(), None);
+
+    play(&mut position, &["e8d8"]);
+    assert_eq!(position.halfmove_clock(), 100);
+    assert_eq!(reason(&position), Some(GameEndReason::FiftyMoveRule));
+
+    // A pawn move starts the count again
+    position.unmake_move();
+    play(&mut position, &["h2h1q"]);
+    assert_eq!(position.halfmove_clock(), 0);
+    assert_eq!(position.game_over(), None);
+}
+
+#[test]
+fn checkmate_on_the_hundredth_half_move_wins() {
+    let mut position = setup(&["kg8", "pf7", "pg7", "ph7", "Ra1", "Kg1"], PieceColor::White);
+    position.set_halfmove_clock(99);
+    play(&mut position, &["a1a8"]);
+    assert_eq!(position.halfmove_clock(), 100);
+    assert_eq!(
+        position.game_over(),
+        Some(GameOver {
+            result: GameResult::Win(PieceColor::White),
+            reason: GameEndReason::Checkmate,
+        })
+    );
+}
+
+#[test]
+fn insufficient_material() {
+    let positions: [&[&str]; 6] = [
+        // King against king
+        &["ke8", "Ke1"],
+        // A lone minor piece
+        &["ke8", "Nc1", "Ke1"],
+        &["ke8", "Bc1", "Ke1"],
+        &["bc8", "ke8", "Ke1"],
+        // Bishops on the same square color, on one side or both
+        &["ke8", "Bd2", "Bc1", "Ke1"],
+        &["bf8", "ke7", "Bc1", "Ke1"],
+    ];
+    for pieces in &positions {
+        let position = setup(pieces, PieceColor::White);
+        assert!(position.is_insufficient_material(), "{:?}", pieces);
+        assert_eq!(
+            reason(&position),
+            Some(GameEndReason::InsufficientMaterial),
+            "{:?}",
+            pieces
+        );
+    }
+}
+
+#[test]
+fn sufficient_material() {
+    let positions: [&[&str]; 7] = [
+        &["ke8", "Pe2", "Ke1"],
+        &["ke8", "Ra1", "Ke1"],
+        &["ke8", "Qd1", "Ke1"],
+        // Bishops on opposite square colors
+        &["ke8", "Bc1", "Ke1", "Bf1"],
+        &["bc8", "ke8", "Bc1", "Ke1"],
+        // Bishop and knight, or two knights
+        &["ke8", "Nb1", "Bc1", "Ke1"],
+        &["nb8", "ke8", "Nb1", "Ke1"],
+    ];
+    for pieces in &positions {
+        let position = setup(pieces, PieceColor::White);
+        assert!(!position.is_insufficient_material(), "{:?}", pieces);
+        assert_eq!(position.game_over(), None, "{:?}", pieces);
+    }
+}
+
+#[test]
+fn capturing_the_last_pawn_draws() {
+    let mut position = setup(&["ke8", "pd2", "Bd1", "Ke1"], PieceColor::White);
+    assert_eq!(position.game_over(), None);
+    play(&mut position, &["e1d2"]);
+    assert_eq!(reason(&position), Some(GameEndReason::InsufficientMaterial));
+}
@@ -0,0 +1,117 @@
+use bevy_chess::{
+    fen::{FenError, START_FEN},
+    position::Position,
+};
+
+mod common;
+use common::play;
+
+#[test]
+fn round_trips() {
+    for fen in &[
+        START_FEN,
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3",
+        "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 b - - 12 40",
+        "4k3/8/8/8/8/8/8/4K2R w K - 99 80",
+    ] {
+        assert_eq!(Position::from_fen(fen).unwrap().to_fen(), *fen);
+    }
+}
+
+#[test]
+fn move_counters_are_optional() {
+    let position = Position::from_fen("4k3/8/8/8/8/8/8/4K3 b - -").unwrap();
+    assert_eq!(position.to_fen(), "4k3/8/8/8/8/8/8/4K3 b - - 0 1");
+}
+
+#[test]
+fn export_after_moves() {
+    let mut position = Position::default();
+    play(&mut position, &["e2e4", "c7c5", "g1f3"]);
+    assert_eq!(
+        position.to_fen(),
+        "rnbqkbnr/pp1ppppp/8/2p5/4P3/5N2/PPPP1PPP/RNBQKB1R b KQkq - 1 2"
+    );
+}
+
+#[test]
+fn rejects_rows_past_the_edge() {
+    for board in &[
+        "99999999/8/8/8/8/8/8/8",
+        "8888/8/8/8/8/8/8/8",
+        "rnbqkbnr/pppppppp/9/8/8/8/PPPPPPPP/RNBQKBNR",
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNRR",
+        "rnbqkbnr/ppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR",
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQ0KBNR",
+    ] {
+        assert_eq!(
+            Position::from_fen(&format!("{} w KQkq - 0 1", board)).err(),
+            Some(FenError::InvalidBoard(board.to_string())),
+            "{}",
+            board
+        );
+    }
+}
+
+#[test]
+fn rejects_malformed_fields() {
+    let err = |fen: &str| Position::from_fen(fen).err();
+    assert_eq!(err(""), Some(FenError::MissingField("piece placement")));
+    assert_eq!(
+        err("4k3/8/8/8/8/8/8/4K3"),
+        Some(FenError::MissingField("side to move"))
+    );
+    assert_eq!(
+        err("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR/8 w KQkq - 0 1"),
+        Some(FenError::InvalidBoard(
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR/8".to_string()
+        ))
+    );
+    assert_eq!(
+        err("4k3/8/8/8/8/8/8/4K3 x - - 0 1"),
+        Some(FenError::InvalidSideToMove("x".to_string()))
+    );
+    assert_eq!(
+        err("4k3/8/8/8/8/8/8/4K3 w KX - 0 1"),
+        Some(FenError::InvalidCastling("KX".to_string()))
+    );
+    assert_eq!(
+        err("4k3/8/8/8/8/8/8/4K3 w - e4 0 1"),
+        Some(FenError::InvalidEnPassant("e4".to_string()))
+    );
+    assert_eq!(
+        err("4k3/8/8/8/8/8/8/4K3 w - - x 1"),
+        Some(FenError::InvalidCounter("x".to_string()))
+    );
+}
+
+#[test]
+fn rejects_impossible_positions() {
+    let err = |fen: &str| Position::from_fen(fen).err();
+
+    // Each side needs exactly one king
+    for board in &[
+        "8/8/8/8/8/8/8/8",
+        "4k3/8/8/8/8/8/8/8",
+        "4k3/8/8/8/8/8/8/3KK3",
+    ] {
+        assert_eq!(
+            err(&format!("{} w - - 0 1", board)),
+            Some(FenError::InvalidBoard(board.to_string())),
+            "{}",
+            board
+        );
+    }
+
+    // Only a pawn of the side that just moved can have skipped the en passant square
+    assert_eq!(
+        err("4k3/8/8/8/4P3/8/8/4K3 w - e3 0 1"),
+        Some(FenError::InvalidEnPassant("e3".to_string()))
+    );
+    assert_eq!(
+        err("4k3/8/8/4p3/8/8/8/4K3 b - e6 0 1"),
+        Some(FenError::InvalidEnPassant("e6".to_string()))
+    );
+    assert!(Position::from_fen("4k3/8/8/8/4P3/8/8/4K3 b - e3 0 1").is_ok());
+}
@@ -0,0 +1,96 @@
+use bevy_chess::{
+    position::{parse_square, Move, Position},
+    transposition::{Bound, Entry, TranspositionTable},
+};
+
+mod common;
+use common::play;
+
+const KIWIPETE: &str = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+
+/// Checks the incrementally updated hash against one computed from scratch at every node
+fn check_hashes(position: &mut Position, depth: u32) {
+    let fresh = Position::from_fen(&position.to_fen()).unwrap();
+    assert_eq!(position.hash(), fresh.hash(), "{}", position.to_fen());
+    if depth == 0 {
+        return;
+    }
+
+    for mv in position.legal_moves() {
+        let before = position.hash();
+        position.make_move(mv);
+        check_hashes(position, depth - 1);
+        position.unmake_move();
+        assert_eq!(position.hash(), before);
+    }
+}
+
+#[test]
+fn make_and_unmake_keep_the_hash_up_to_date() {
+    check_hashes(&mut Position::from_fen(KIWIPETE).unwrap(), 3);
+}
+
+#[test]
+fn transpositions_hash_the_same() {
+    let mut a = Position::default();
+    play(&mut a, &["g1f3", "g8f6", "b1c3"]);
+    let mut b = Position::default();
+    play(&mut b, &["b1c3", "g8f6", "g1f3"]);
+    assert_eq!(a.hash(), b.hash());
+
+    // Same pieces but a different side to move
+    let mut c = Position::default();
+    play(&mut c, &["g1f3", "g8f6", "b1c3", "b8c6"]);
+    assert_ne!(a.hash(), c.hash());
+}
+
+#[test]
+fn en_passant_only_counts_when_it_can_be_taken() {
+    let mut position = Position::default();
+    play(&mut position, &["e2e4"]);
+    let without = Position::from_fen(&position.to_fen().replace(" e3 ", " - ")).unwrap();
+    assert_eq!(position.hash(), without.hash());
+
+    let mut position = Position::from_fen("4k3/8/8/8/3p4/8/4P3/4K3 w - - 0 1").unwrap();
+    play(&mut position, &["e2e4"]);
+    let without = Position::from_fen(&position.to_fen().replace(" e3 ", " - ")).unwrap();
+    assert_ne!(position.hash(), without.hash());
+}
+
+fn entry(hash: u64, depth: u32) -> Entry {
+    Entry {
+        hash,
+        depth,
+        score: depth as i32,
+        bound: Bound::Exact,
+        best_move: Some(Move::new(
+            parse_square("e2").unwrap(),
+            parse_square("e4").unwrap(),
+        )),
+    }
+}
+
+#[test]
+fn table_keeps_the_deeper_entry() {
+    let mut table = TranspositionTable::new(1);
+    let size = table.len() as u64;
+    assert!(size.is_power_of_two());
+
+    table.store(entry(5, 4));
+    assert_eq!(table.probe(5), Some(entry(5, 4)));
+    assert_eq!(table.probe(5 + size), None);
+
+    // A different position in the same slot only replaces it if searched as deep
+    table.store(entry(5 + size, 3));
+    assert_eq!(table.probe(5), Some(entry(5, 4)));
+    table.store(entry(5 + size, 4));
+    assert_eq!(table.probe(5), None);
+    assert_eq!(table.probe(5 + size), Some(entry(5 + size, 4)));
+
+    // The same position is always updated
+    table.store(entry(5 + size, 1));
+    assert_eq!(table.probe(5 + size), Some(entry(5 + size, 1)));
+
+    table.clear();
+    assert_eq!(table.probe(5 + size), None);
+}
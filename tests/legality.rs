@@ -0,0 +1,163 @@
+use bevy_chess::position::{GameEndReason, GameOver, GameResult, PieceColor, Position};
+
+mod common;
+use common::{play, setup, square, to_uci};
+
+/// Legal moves in UCI notation, sorted
+fn legal_moves(position: &Position) -> Vec<String> {
+    let mut moves: Vec<String> = position.legal_moves().into_iter().map(to_uci).collect();
+    moves.sort();
+    moves
+}
+
+fn legal_moves_from(position: &Position, from: &str) -> Vec<String> {
+    let mut moves: Vec<String> = position
+        .legal_moves_from(square(from))
+        .into_iter()
+        .map(to_uci)
+        .collect();
+    moves.sort();
+    moves
+}
+
+#[test]
+fn detects_check() {
+    let position = setup(&["ke8", "ra1", "Ke1"], PieceColor::White);
+    assert!(position.is_in_check(PieceColor::White));
+    assert!(!position.is_in_check(PieceColor::Black));
+
+    // A blocked line gives no check
+    let position = setup(&["ke8", "ra1", "Nd1", "Ke1"], PieceColor::White);
+    assert!(!position.is_in_check(PieceColor::White));
+}
+
+#[test]
+fn king_cannot_stay_on_the_checking_line() {
+    // Stepping along the rank keeps the king in the rook's line
+    let position = setup(&["ke8", "ra1", "Ke1"], PieceColor::White);
+    assert_eq!(legal_moves(&position), vec!["e1d2", "e1e2", "e1f2"]);
+}
+
+#[test]
+fn check_must_be_answered() {
+    // Take the rook, block the file, or step aside
+    let position = setup(&["re8", "kg8", "Ba4", "Bc1", "Ke1", "Ng1"], PieceColor::White);
+    assert_eq!(
+        legal_moves(&position),
+        vec!["a4e8", "c1e3", "e1d1", "e1d2", "e1f1", "e1f2", "g1e2"]
+    );
+}
+
+#[test]
+fn pinned_pieces_stay_on_the_pin_line() {
+    // A pinned bishop can't move at all
+    let position = setup(&["ke8", "re7", "Be2", "Ke1"], PieceColor::White);
+    assert!(legal_moves_from(&position, "e2").is_empty());
+
+    // A pinned rook can only move along the file, up to taking the pinning rook
+    let position = setup(&["ke8", "re7", "Re2", "Ke1"], PieceColor::White);
+    assert_eq!(
+        legal_moves_from(&position, "e2"),
+        vec!["e2e3", "e2e4", "e2e5", "e2e6", "e2e7"]
+    );
+
+    // A pinned pawn can't push, but it can take the pinning bishop
+    let position = setup(&["ke8", "ba5", "Pc3", "Ke1"], PieceColor::White);
+    assert!(legal_moves_from(&position, "c3").is_empty());
+    let position = setup(&["ke8", "bb3", "Pc2", "Kd1"], PieceColor::White);
+    assert_eq!(legal_moves_from(&position, "c2"), vec!["c2b3"]);
+}
+
+#[test]
+fn discovered_check() {
+    // Any knight move opens the rook's file onto the black king
+    let mut position = setup(&["ke8", "Ne2", "Re1", "Kg1"], PieceColor::White);
+    play(&mut position, &["e2c3"]);
+    assert!(position.is_in_check(PieceColor::Black));
+    assert_eq!(legal_moves(&position), vec!["e8d7", "e8d8", "e8f7", "e8f8"]);
+}
+
+#[test]
+fn double_check_only_allows_king_moves() {
+    // The knight and the rook both give check, so taking the knight isn't enough
+    let position = setup(&["qd8", "ke8", "Nf6", "Re1", "Kg1"], PieceColor::Black);
+    assert!(position.is_in_check(PieceColor::Black));
+    assert!(!position.legal_moves().is_empty());
+    assert!(position
+        .legal_moves()
+        .iter()
+        .all(|mv| mv.from == square("e8")));
+}
+
+#[test]
+fn en_passant_cannot_expose_the_king() {
+    // Taking en passant would clear both pawns off the rank between the king and the rook
+    let mut position = setup(&["Ka5", "Pb5", "pc5", "rh5", "ke1"], PieceColor::White);
+    position.set_en_passant(Some(square("c6")));
+    assert!(!legal_moves(&position).contains(&"b5c6".to_string()));
+    assert!(legal_moves(&position).contains(&"b5b6".to_string()));
+}
+
+#[test]
+fn en_passant_can_take_the_checking_pawn() {
+    let mut position = setup(&["kc5", "Pd4", "pe4", "Ke1"], PieceColor::Black);
+    position.set_en_passant(Some(square("d3")));
+    assert!(position.is_in_check(PieceColor::Black));
+    assert!(legal_moves(&position).contains(&"e4d3".to_string()));
+}
+
+#[test]
+fn fools_mate() {
+    let mut position = Position::default();
+    play(&mut position, &["f2f3", "e7e5", "g2g4", "d8h4"]);
+    assert!(position.legal_moves().is_empty());
+    assert_eq!(
+        position.game_over(),
+        Some(GameOver {
+            result: GameResult::Win(PieceColor::Black),
+            reason: GameEndReason::Checkmate,
+        })
+    );
+}
+
+#[test]
+fn smothered_mate() {
+    let position = setup(&["rg8", "kh8", "Nf7", "pg7", "ph7", "Kg1"], PieceColor::Black);
+    assert_eq!(
+        position.game_over(),
+        Some(GameOver {
+            result: GameResult::Win(PieceColor::White),
+            reason: GameEndReason::Checkmate,
+        })
+    );
+}
+
+#[test]
+fn check_with_a_way_out_is_not_mate() {
+    // The king can take the unprotected queen
+    let position = setup(&["kh8", "Qg7", "Kg1"], PieceColor::Black);
+    assert!(position.is_in_check(PieceColor::Black));
+    assert_eq!(legal_moves(&position), vec!["h8g7"]);
+    assert_eq!(position.game_over(), None);
+}
+
+#[test]
+fn stalemate() {
+    let position = setup(&["kh8", "Qf7", "Kg6"], PieceColor::Black);
+    assert!(!position.is_in_check(PieceColor::Black));
+    assert!(position.legal_moves().is_empty());
+    assert_eq!(
+        position.game_over(),
+        Some(GameOver {
+            result: GameResult::Draw,
+            reason: GameEndReason::Stalemate,
+        })
+    );
+
+    // A pawn that can't move doesn't help
+    let position = setup(&["kh8", "Qf7", "Kg6", "pa5", "Pa4"], PieceColor::Black);
+    assert_eq!(
+        position.game_over().map(|game_over| game_over.reason),
+        Some(GameEndReason::Stalemate)
+    );
+}
@@ -0,0 +1,58 @@
+use bevy_chess::position::{PieceColor, PieceType, Position};
+
+mod common;
+use common::play;
+
+#[test]
+fn starting_material() {
+    let position = Position::default();
+    assert_eq!(position.material(PieceColor::White), 39);
+    assert_eq!(position.material(PieceColor::Black), 39);
+    assert_eq!(position.captured_pieces().count(), 0);
+}
+
+#[test]
+fn captures_in_order() {
+    let mut position = Position::default();
+    // Pawns trade on d5, then the queen takes on d2 and the bishop takes it back
+    play(
+        &mut position,
+        &["e2e4", "d7d5", "e4d5", "d8d5", "b1c3", "d5d2", "c1d2"],
+    );
+
+    let captured: Vec<_> = position.captured_pieces().collect();
+    assert_eq!(
+        captured,
+        vec![
+            (PieceColor::Black, PieceType::Pawn),
+            (PieceColor::White, PieceType::Pawn),
+            (PieceColor::White, PieceType::Pawn),
+            (PieceColor::Black, PieceType::Queen),
+        ]
+    );
+    assert_eq!(position.material(PieceColor::White), 37);
+    assert_eq!(position.material(PieceColor::Black), 29);
+
+    position.unmake_move();
+    assert_eq!(position.captured_pieces().count(), 3);
+}
+
+#[test]
+fn promotions_count_as_the_new_piece() {
+    let mut position = Position::from_fen("4k3/P7/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+    assert_eq!(position.material(PieceColor::White), 1);
+
+    play(&mut position, &["a7a8q"]);
+    assert_eq!(position.material(PieceColor::White), 9);
+    assert_eq!(position.material(PieceColor::Black), 0);
+}
+
+#[test]
+fn en_passant_captures_the_pawn() {
+    let mut position = Position::from_fen("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 2").unwrap();
+    play(&mut position, &["e5d6"]);
+    assert_eq!(
+        position.captured_pieces().collect::<Vec<_>>(),
+        vec![(PieceColor::Black, PieceType::Pawn)]
+    );
+}
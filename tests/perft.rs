@@ -0,0 +1,82 @@
+use bevy_chess::{fen::START_FEN, position::Position};
+
+const KIWIPETE: &str = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+const POSITION_3: &str = "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1";
+const POSITION_4: &str = "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1";
+const POSITION_5: &str = "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8";
+const POSITION_6: &str = "r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10";
+
+fn assert_perft(fen: &str, counts: &[u64]) {
+    let position = Position::from_fen(fen).unwrap();
+    for (depth, &count) in counts.iter().enumerate() {
+        let depth = depth as u32 + 1;
+        assert_eq!(position.perft(depth), count, "perft({}) of {}", depth, fen);
+    }
+}
+
+#[test]
+fn start_position() {
+    assert_perft(START_FEN, &[20, 400, 8_902, 197_281]);
+}
+
+#[test]
+fn kiwipete() {
+    assert_perft(KIWIPETE, &[48, 2_039, 97_862]);
+}
+
+#[test]
+fn position_3() {
+    assert_perft(POSITION_3, &[14, 191, 2_812, 43_238]);
+}
+
+#[test]
+fn position_4() {
+    assert_perft(POSITION_4, &[6, 264, 9_467]);
+}
+
+#[test]
+fn position_5() {
+    assert_perft(POSITION_5, &[44, 1_486, 62_379]);
+}
+
+#[test]
+fn position_6() {
+    assert_perft(POSITION_6, &[46, 2_079, 89_890]);
+}
+
+#[test]
+fn castling_through_attacks() {
+    assert_perft("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1", &[26, 568, 13_744]);
+}
+
+#[test]
+fn promotions() {
+    assert_perft("n1n5/PPPk4/8/8/8/8/4Kppp/5N1N b - - 0 1", &[24, 496, 9_483]);
+}
+
+#[test]
+fn en_passant_pins() {
+    // Taking en passant would expose the king along the rank
+    assert_perft("8/8/8/K2pP2r/8/8/8/7k w - d6 0 1", &[6]);
+}
+
+#[test]
+fn divide_adds_up_to_perft() {
+    let position = Position::from_fen(KIWIPETE).unwrap();
+    let divide = position.divide(2);
+    assert_eq!(divide.len(), 48);
+    assert_eq!(divide.iter().map(|(_, nodes)| nodes).sum::<u64>(), 2_039);
+}
+
+// Too slow for every run, try them with `cargo test --release -- --ignored`
+#[test]
+#[ignore]
+fn deep_start_position() {
+    assert_perft(START_FEN, &[20, 400, 8_902, 197_281, 4_865_609]);
+}
+
+#[test]
+#[ignore]
+fn deep_kiwipete() {
+    assert_perft(KIWIPETE, &[48, 2_039, 97_862, 4_085_603]);
+}
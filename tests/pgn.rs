@@ -0,0 +1,205 @@
+use bevy_chess::{
+    pgn::{parse_pgn, GameRecord, PgnError},
+    position::{GameResult, PieceColor, Position},
+};
+
+mod common;
+use common::{play, uci_move};
+
+#[test]
+fn castling_written_with_zeros() {
+    let games = parse_pgn("1. e4 e5 2. Nf3 Nc6 3. Bc4 Bc5 4. 0-0 Nf6 5.d3 0-0 *").unwrap();
+    assert_eq!(
+        games[0].san_moves(),
+        vec!["e4", "e5", "Nf3", "Nc6", "Bc4", "Bc5", "O-O", "Nf6", "d3", "O-O"]
+    );
+}
+
+fn record(start: Position, moves: &[&str]) -> GameRecord {
+    let mut record = GameRecord::new(start);
+    let mut position = record.start.clone();
+    for uci in moves {
+        play(&mut position, &[uci]);
+        record.moves.push(uci_move(uci));
+    }
+    record
+}
+
+#[test]
+fn exports_tags_and_moves() {
+    let mut record = record(Position::default(), &["f2f3", "e7e5", "g2g4", "d8h4"]);
+    record.set_tag("White", "Fool \"the\" Player");
+    record.set_result(Some(GameResult::Win(PieceColor::Black)));
+
+    assert_eq!(
+        record.to_pgn(),
+        "[Event \"?\"]\n\
+         [Site \"?\"]\n\
+         [Date \"????.??.??\"]\n\
+         [Round \"?\"]\n\
+         [White \"Fool \\\"the\\\" Player\"]\n\
+         [Black \"?\"]\n\
+         [Result \"0-1\"]\n\
+         \n\
+         1. f3 e5 2. g4 Qh4# 0-1\n"
+    );
+}
+
+#[test]
+fn exports_the_starting_position() {
+    let start = Position::from_fen("4k3/8/8/8/8/8/4P3/4K3 b - - 0 10").unwrap();
+    let pgn = record(start, &["e8d7", "e2e4"]).to_pgn();
+
+    assert!(pgn.contains("[SetUp \"1\"]\n[FEN \"4k3/8/8/8/8/8/4P3/4K3 b - - 0 10\"]\n"));
+    assert!(pgn.ends_with("\n10... Kd7 11. e4 *\n"), "{}", pgn);
+}
+
+#[test]
+fn wraps_long_games() {
+    // Knights shuffling back and forth
+    let moves: Vec<&str> = ["g1f3", "g8f6", "f3g1", "f6g8"]
+        .iter()
+        .cycle()
+        .take(40)
+        .copied()
+        .collect();
+    let pgn = record(Position::default(), &moves).to_pgn();
+
+    let movetext: Vec<&str> = pgn.split("\n\n").nth(1).unwrap().lines().collect();
+    assert!(movetext.len() > 1);
+    for line in &movetext {
+        assert!(line.len() < 80, "{}", line);
+        // Move numbers stay with their moves
+        assert!(!line.ends_with('.'), "{}", line);
+    }
+}
+
+#[test]
+fn exported_games_read_back() {
+    let moves = [
+        "e2e4", "e7e5", "g1f3", "b8c6", "f1c4", "f8c5", "e1g1", "g8f6", "d2d4", "e5d4", "e4e5",
+        "d7d5", "e5d6", "d8d6", "f1e1", "e8f8",
+    ];
+    let original = record(Position::default(), &moves);
+    let games = parse_pgn(&original.to_pgn()).unwrap();
+
+    assert_eq!(games.len(), 1);
+    assert_eq!(games[0].moves, original.moves);
+    assert_eq!(games[0].tags, original.tags);
+}
+
+#[test]
+fn imports_several_games() {
+    let text = r#"[Event "First"]
+[Result "1-0"]
+
+1. e4 e5 2. Qh5 Nc6 3. Bc4 Nf6 4. Qxf7# 1-0
+
+[Event "Second"]
+
+1. d4 d5 1/2-1/2
+
+[Event "Third"]
+1.c4 e5
+[Event "Fourth"]
+*
+"#;
+    let games = parse_pgn(text).unwrap();
+
+    assert_eq!(games.len(), 4);
+    assert_eq!(games[0].tag("Event"), Some("First"));
+    assert_eq!(games[0].moves.len(), 7);
+    assert_eq!(games[0].tag("Result"), Some("1-0"));
+    assert_eq!(games[1].tag("Event"), Some("Second"));
+    assert_eq!(games[1].san_moves(), vec!["d4", "d5"]);
+    assert_eq!(games[1].tag("Result"), Some("1/2-1/2"));
+    // Tags after moves start the next game, even without a result
+    assert_eq!(games[2].san_moves(), vec!["c4", "e5"]);
+    assert_eq!(games[2].tag("Result"), Some("*"));
+    assert_eq!(games[3].tag("Event"), Some("Fourth"));
+    assert!(games[3].moves.is_empty());
+}
+
+#[test]
+fn skips_comments_nags_and_variations() {
+    let text = r#"
+% An escaped line, 1. a4
+[Event "Annotated"]
+
+1. e4 {The king's pawn, 1. d4 would be (fine) too} e5 $1 ; Rest of line: 2. a3
+2. Nf3 (2. f4 exf4 (2... d5) 3. Nf3) Nc6!? 3.Bb5 {a move number glued on} 3... a6 0-1
+"#;
+    let games = parse_pgn(text).unwrap();
+
+    assert_eq!(games.len(), 1);
+    assert_eq!(
+        games[0].san_moves(),
+        vec!["e4", "e5", "Nf3", "Nc6", "Bb5", "a6"]
+    );
+    assert_eq!(games[0].tag("Result"), Some("0-1"));
+}
+
+#[test]
+fn imports_a_starting_position() {
+    let text = r#"[SetUp "1"]
+[FEN "4k3/8/8/8/8/8/4P3/4K3 b - - 0 10"]
+
+10... Kd7 11. e4 *
+"#;
+    let games = parse_pgn(text).unwrap();
+
+    assert_eq!(
+        games[0].position().to_fen(),
+        "8/3k4/8/8/4P3/8/8/4K3 b - e3 0 11"
+    );
+    // The position is kept as the record's start, not as tags
+    assert_eq!(games[0].tag("FEN"), None);
+    assert_eq!(games[0].tag("SetUp"), None);
+}
+
+#[test]
+fn reads_escaped_tag_values() {
+    let games = parse_pgn(r#"[White "A \"quoted\" name, with a \\ and a ]"] *"#).unwrap();
+    assert_eq!(
+        games[0].tag("White"),
+        Some(r#"A "quoted" name, with a \ and a ]"#)
+    );
+}
+
+#[test]
+fn rejects_malformed_games() {
+    assert_eq!(
+        parse_pgn("[Event] *").err(),
+        Some(PgnError::InvalidTag("Event".to_string()))
+    );
+    assert_eq!(
+        parse_pgn("[Event First] *").err(),
+        Some(PgnError::InvalidTag("Event First".to_string()))
+    );
+    assert!(matches!(
+        parse_pgn("[FEN \"8/8/8\"] *"),
+        Err(PgnError::InvalidFen(_))
+    ));
+    assert_eq!(
+        parse_pgn("1. e4 e5 * 1. e4 e5 2. Ke3 *").err(),
+        Some(PgnError::IllegalMove {
+            game: 1,
+            ply: 2,
+            san: "Ke3".to_string()
+        })
+    );
+    assert_eq!(
+        parse_pgn("1. e4 Nf3 *").err(),
+        Some(PgnError::IllegalMove {
+            game: 0,
+            ply: 1,
+            san: "Nf3".to_string()
+        })
+    );
+}
+
+#[test]
+fn empty_text_has_no_games() {
+    assert!(parse_pgn("").unwrap().is_empty());
+    assert!(parse_pgn("  \n{just a comment}\n").unwrap().is_empty());
+}
@@ -0,0 +1,140 @@
+use bevy_chess::position::Position;
+
+mod common;
+use common::{to_uci, uci_move};
+
+/// Writes the move given in UCI notation as SAN
+fn san(fen: &str, uci: &str) -> String {
+    let position = Position::from_fen(fen).unwrap();
+    position.to_san(uci_move(uci))
+}
+
+/// Reads the SAN move, returning it in UCI notation
+fn parse(fen: &str, san: &str) -> Option<String> {
+    Position::from_fen(fen)
+        .unwrap()
+        .parse_san(san)
+        .map(to_uci)
+}
+
+#[test]
+fn pawn_and_piece_moves() {
+    let start = Position::default().to_fen();
+    assert_eq!(san(&start, "e2e4"), "e4");
+    assert_eq!(san(&start, "g1f3"), "Nf3");
+
+    let fen = "rnbqkbnr/ppp1pppp/8/3p4/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 0 2";
+    assert_eq!(san(fen, "e4d5"), "exd5");
+    assert_eq!(san(fen, "f1b5"), "Bb5+");
+}
+
+#[test]
+fn en_passant_is_a_pawn_capture() {
+    let fen = "4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 2";
+    assert_eq!(san(fen, "e5d6"), "exd6");
+    assert_eq!(parse(fen, "exd6").as_deref(), Some("e5d6"));
+}
+
+#[test]
+fn disambiguation() {
+    // Knights on b1 and f1 both reach d2, and rooks on a1 and a5 both reach a3
+    let fen = "4k3/8/8/R7/8/8/8/RN2KN2 w - - 0 1";
+    assert_eq!(san(fen, "b1d2"), "Nbd2");
+    assert_eq!(san(fen, "f1d2"), "Nfd2");
+    assert_eq!(san(fen, "a1a3"), "R1a3");
+    assert_eq!(san(fen, "a5a3"), "R5a3");
+    // Only one knight reaches c3
+    assert_eq!(san(fen, "b1c3"), "Nc3");
+
+    // Queens on a1, a4 and d1 all reach d4, so a1 needs both file and rank
+    let fen = "8/7k/8/8/Q7/8/8/Q2QK3 w - - 0 1";
+    assert_eq!(san(fen, "a1d4"), "Qa1d4");
+    assert_eq!(san(fen, "a4d4"), "Q4d4");
+    assert_eq!(san(fen, "d1d4"), "Qdd4");
+}
+
+#[test]
+fn pinned_pieces_need_no_disambiguation() {
+    // The knight on e2 is pinned, so only the one on b1 can go to c3
+    let fen = "4r1k1/8/8/8/8/8/4N3/1N2K3 w - - 0 1";
+    assert_eq!(san(fen, "b1c3"), "Nc3");
+    assert_eq!(parse(fen, "Nc3").as_deref(), Some("b1c3"));
+}
+
+#[test]
+fn check_and_mate_suffixes() {
+    let fen = "6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1";
+    assert_eq!(san(fen, "a1a8"), "Ra8#");
+    assert_eq!(san(fen, "a1a7"), "Ra7");
+
+    let fen = "6k1/5pp1/8/8/8/8/8/R5K1 w - - 0 1";
+    assert_eq!(san(fen, "a1a8"), "Ra8+");
+}
+
+#[test]
+fn promotions() {
+    let fen = "1n2k3/P7/8/8/8/8/8/4K3 w - - 0 1";
+    assert_eq!(san(fen, "a7a8q"), "a8=Q");
+    assert_eq!(san(fen, "a7a8n"), "a8=N");
+    assert_eq!(san(fen, "a7b8r"), "axb8=R+");
+
+    assert_eq!(parse(fen, "a8=Q").as_deref(), Some("a7a8q"));
+    assert_eq!(parse(fen, "axb8=N").as_deref(), Some("a7b8n"));
+    // Some writers leave out the `=`
+    assert_eq!(parse(fen, "a8Q").as_deref(), Some("a7a8q"));
+    // A pawn on the last rank has to promote
+    assert_eq!(parse(fen, "a8"), None);
+}
+
+#[test]
+fn castling() {
+    let fen = "r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1";
+    assert_eq!(san(fen, "e1g1"), "O-O");
+    assert_eq!(san(fen, "e1c1"), "O-O-O");
+
+    for (written, uci) in &[
+        ("O-O", "e1g1"),
+        ("O-O-O", "e1c1"),
+        ("0-0", "e1g1"),
+        ("0-0-0", "e1c1"),
+        ("O-O+", "e1g1"),
+    ] {
+        assert_eq!(parse(fen, written).as_deref(), Some(*uci), "{}", written);
+    }
+
+    // No castling through check
+    let fen = "4k3/8/8/8/8/8/5r2/R3K2R w KQ - 0 1";
+    assert_eq!(parse(fen, "O-O"), None);
+}
+
+#[test]
+fn parsing_ignores_check_marks_and_annotations() {
+    let start = Position::default().to_fen();
+    assert_eq!(parse(&start, "e4!?").as_deref(), Some("e2e4"));
+    assert_eq!(parse(&start, "Nf3+").as_deref(), Some("g1f3"));
+    assert_eq!(parse(&start, "Ng1-f3").as_deref(), Some("g1f3"));
+}
+
+#[test]
+fn rejects_illegal_and_ambiguous_moves() {
+    let start = Position::default().to_fen();
+    assert_eq!(parse(&start, "e5"), None);
+    assert_eq!(parse(&start, "Ke2"), None);
+    assert_eq!(parse(&start, "Zf3"), None);
+    assert_eq!(parse(&start, ""), None);
+    assert_eq!(parse(&start, "x"), None);
+
+    let fen = "4k3/8/8/8/8/8/8/1N2KN2 w - - 0 1";
+    assert_eq!(parse(fen, "Nd2"), None);
+    assert_eq!(parse(fen, "Nbd2").as_deref(), Some("b1d2"));
+}
+
+#[test]
+fn round_trips_every_legal_move() {
+    let fen = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+    let position = Position::from_fen(fen).unwrap();
+    for mv in position.legal_moves() {
+        let san = position.to_san(mv);
+        assert_eq!(position.parse_san(&san), Some(mv), "{}", san);
+    }
+}
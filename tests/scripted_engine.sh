@@ -0,0 +1,11 @@
+#!/bin/sh
+# Stand-in UCI engine for tests. It answers the handshake and replies to each `go` with the
+# next of the moves given as arguments, whatever the position.
+while read -r line; do
+    case "$line" in
+        uci) echo "id name Scripted"; echo "uciok" ;;
+        isready) echo "readyok" ;;
+        go*) echo "info depth 1"; echo "bestmove $1"; shift ;;
+        quit) exit 0 ;;
+    esac
+done
@@ -0,0 +1,85 @@
+use bevy_chess::{position::Position, uci::UciEngine};
+use std::{io, time::Duration};
+
+mod common;
+use common::uci_move;
+
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+// The stand-in engine is a shell script
+#[cfg(unix)]
+fn scripted_engine(moves: &str) -> UciEngine {
+    let script = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/scripted_engine.sh");
+    UciEngine::spawn(&format!("sh {} {}", script, moves), TIMEOUT).unwrap()
+}
+
+#[test]
+#[cfg(unix)]
+fn plays_the_engine_move() {
+    let mut engine = scripted_engine("e7e5");
+    engine
+        .start_search(
+            &Position::default(),
+            &[uci_move("e2e4")],
+            Duration::from_millis(10),
+        )
+        .unwrap();
+    assert_eq!(
+        engine.wait_best_move(TIMEOUT).unwrap(),
+        Some(uci_move("e7e5"))
+    );
+}
+
+#[test]
+#[cfg(unix)]
+fn ignores_the_move_of_a_stopped_search() {
+    let mut engine = scripted_engine("e7e5 d7d5");
+    let start = Position::default();
+    engine
+        .start_search(&start, &[uci_move("e2e4")], Duration::from_millis(10))
+        .unwrap();
+    // The board changed before the reply was read, like after an undo
+    engine
+        .start_search(&start, &[uci_move("d2d4")], Duration::from_millis(10))
+        .unwrap();
+    assert_eq!(
+        engine.wait_best_move(TIMEOUT).unwrap(),
+        Some(uci_move("d7d5"))
+    );
+}
+
+#[test]
+#[cfg(unix)]
+fn rejects_an_illegal_engine_move() {
+    let mut engine = scripted_engine("e2e4");
+    engine
+        .start_search(
+            &Position::default(),
+            &[uci_move("e2e4")],
+            Duration::from_millis(10),
+        )
+        .unwrap();
+    let err = engine.wait_best_move(TIMEOUT).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+}
+
+#[test]
+fn fails_to_spawn_a_missing_engine() {
+    assert!(UciEngine::spawn("./no-such-engine", TIMEOUT).is_err());
+    assert!(UciEngine::spawn("", TIMEOUT).is_err());
+}
+
+#[test]
+fn plays_against_the_uci_server() {
+    let mut engine = UciEngine::spawn(env!("CARGO_BIN_EXE_bevy_chess-uci"), TIMEOUT).unwrap();
+    let start = Position::default();
+    let moves = [uci_move("e2e4")];
+    engine
+        .start_search(&start, &moves, Duration::from_millis(100))
+        .unwrap();
+
+    let mv = engine.wait_best_move(TIMEOUT).unwrap().unwrap();
+    let mut position = start;
+    position.make_move(moves[0]);
+    assert!(position.legal_moves().contains(&mv));
+}